@@ -1,4 +1,5 @@
-use std::io;
+use std::env;
+use std::io::{self, Write};
 
 #[derive(Debug, Copy, Clone)]
 enum Direction {
@@ -6,6 +7,26 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn symbol(&self) -> char {
+        match self {
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+}
+
+/// A single move of the dial together with its effect on the password.
+#[derive(Debug, Copy, Clone)]
+struct Step {
+    direction: Direction,
+    distance: i32,
+    start: i32,
+    end: i32,
+    zero_hits: u64,
+    total_zero_hits: u64,
+}
+
 fn parse_line(line: &str) -> (Direction, i32) {
     let line = line.trim();
     let (direction, distance) = line.split_at(1);
@@ -61,22 +82,111 @@ fn count_zero_hits_during_move(position: i32, direction: Direction, distance: i3
     }
 }
 
-fn process_input(input: &str) -> u64 {
+fn build_timeline(input: &str) -> Vec<Step> {
     let mut position: i32 = 50; // dial starts at 50
     let mut zero_hits: u64 = 0;
 
-    for line in input.lines().filter(|l| !l.trim().is_empty()) {
-        let (direction, distance) = parse_line(line);
-        zero_hits += count_zero_hits_during_move(position, direction, distance);
-        position = apply_move(position, direction, distance);
+    input
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|line| {
+            let (direction, distance) = parse_line(line);
+            let hits = count_zero_hits_during_move(position, direction, distance);
+            let start = position;
+
+            zero_hits += hits;
+            position = apply_move(position, direction, distance);
+
+            Step {
+                direction,
+                distance,
+                start,
+                end: position,
+                zero_hits: hits,
+                total_zero_hits: zero_hits,
+            }
+        })
+        .collect()
+}
+
+fn process_input(input: &str) -> u64 {
+    build_timeline(input)
+        .last()
+        .map_or(0, |step| step.total_zero_hits)
+}
+
+fn export_timeline_csv(timeline: &[Step], out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "step,direction,distance,start,end,zero_hits,total_zero_hits"
+    )?;
+    for (idx, step) in timeline.iter().enumerate() {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            idx + 1,
+            step.direction.symbol(),
+            step.distance,
+            step.start,
+            step.end,
+            step.zero_hits,
+            step.total_zero_hits
+        )?;
     }
+    Ok(())
+}
+
+/// Renders the dial face unrolled into a strip: labels every 10 clicks,
+/// a tick per position and a pointer under the current one.
+fn render_dial(position: i32) -> String {
+    let position = position.rem_euclid(100) as usize;
+
+    let labels = (0..100)
+        .step_by(10)
+        .map(|mark| format!("{mark:<10}"))
+        .collect::<String>();
+    let ticks = (0..100)
+        .map(|mark| if mark % 10 == 0 { '|' } else { '\'' })
+        .collect::<String>();
+    let pointer = format!("{:>width$}", '^', width = position + 1);
+
+    format!("{}\n{}\n{}", labels.trim_end(), ticks, pointer)
+}
 
-    zero_hits
+fn print_dial_timeline(timeline: &[Step]) {
+    println!("start\n{}\n", render_dial(50));
+    for (idx, step) in timeline.iter().enumerate() {
+        println!(
+            "step {}: {}{} {} -> {} (zero hits: {}, total: {})",
+            idx + 1,
+            step.direction.symbol(),
+            step.distance,
+            step.start,
+            step.end,
+            step.zero_hits,
+            step.total_zero_hits
+        );
+        println!("{}\n", render_dial(step.end));
+    }
 }
 
 fn main() -> io::Result<()> {
     let input = include_str!("input.data");
 
+    // optional modes: `--csv` dumps the timeline, `--dial` draws every step
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let export_csv = args.iter().any(|arg| arg == "--csv");
+    let show_dial = args.iter().any(|arg| arg == "--dial");
+
+    if export_csv {
+        let timeline = build_timeline(input);
+        return export_timeline_csv(&timeline, &mut io::stdout().lock());
+    }
+
+    if show_dial {
+        print_dial_timeline(&build_timeline(input));
+    }
+
     let zero_hits = process_input(input);
 
     println!("The actual password is {zero_hits}");
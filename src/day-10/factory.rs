@@ -142,7 +142,6 @@ impl FromStr for Machine {
         let start = input.find('(').expect("missing buttons start marker");
         let end = input.rfind(')').expect("missing buttons end marker");
         let buttons = input[start..end + 1]
-            .trim()
             .split_whitespace()
             .map(|part| parse_numbers(&part[1..part.len() - 1]))
             .collect::<Vec<_>>();
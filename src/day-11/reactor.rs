@@ -92,7 +92,7 @@ fn collect_all_paths(devices: &HashMap<String, Device>, from: &str, to: &str) ->
 
 fn count_paths(devices: &HashMap<String, Device>, from: &str, to: &str) -> usize {
     // it's not optimal, but run in a blink under debug, so it meets my personal requirements
-    collect_all_paths(&devices, from, to).len()
+    collect_all_paths(devices, from, to).len()
 }
 
 /// Assumes: graph is a DAG
@@ -146,8 +146,8 @@ fn count_paths_with_checkpoints(
     // 3) topological sort (Kahn)
     let mut topo = Vec::with_capacity(outs_count);
     let mut q = VecDeque::new();
-    for v in 0..outs_count {
-        if indegree[v] == 0 {
+    for (v, &degree) in indegree.iter().enumerate() {
+        if degree == 0 {
             q.push_back(v);
         }
     }
@@ -1,10 +1,17 @@
+use std::env;
+use std::fmt;
 use std::io;
+use std::iter;
 use std::str::FromStr;
 
-#[derive(Debug)]
+mod rules;
+
+use rules::Rule;
+
+#[derive(Debug, Copy, Clone)]
 struct Range {
-    first: i64,
-    last: i64,
+    first: u64,
+    last: u64,
 }
 
 impl FromStr for Range {
@@ -13,13 +20,148 @@ impl FromStr for Range {
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let (first, last) = input.split_once('-').ok_or("Could not parse range")?;
 
-        let first = first.trim().parse::<i64>().map_err(|_| "Bad first")?;
-        let last = last.trim().parse::<i64>().map_err(|_| "Bad last")?;
+        let first = first.trim().parse::<u64>().map_err(|_| "Bad first")?;
+        let last = last.trim().parse::<u64>().map_err(|_| "Bad last")?;
 
         Ok(Range { first, last })
     }
 }
 
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.first, self.last)
+    }
+}
+
+impl Range {
+    fn is_reversed(&self) -> bool {
+        self.first > self.last
+    }
+
+    fn normalized(&self) -> Range {
+        Range {
+            first: self.first.min(self.last),
+            last: self.first.max(self.last),
+        }
+    }
+
+    fn overlaps(&self, other: &Range) -> bool {
+        self.first <= other.last && other.first <= self.last
+    }
+
+    fn touches(&self, other: &Range) -> bool {
+        self.last.checked_add(1) == Some(other.first)
+            || other.last.checked_add(1) == Some(self.first)
+    }
+}
+
+#[derive(Debug)]
+enum RangeWarning {
+    Reversed(usize, Range),
+    Overlapping((usize, Range), (usize, Range)),
+    Adjacent((usize, Range), (usize, Range)),
+}
+
+impl fmt::Display for RangeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ranges are numbered from 1 in input order
+        match self {
+            RangeWarning::Reversed(idx, range) => {
+                write!(f, "range #{} ({range}) is reversed", idx + 1)
+            }
+            RangeWarning::Overlapping((a, first), (b, second)) => {
+                write!(
+                    f,
+                    "ranges #{} ({first}) and #{} ({second}) overlap",
+                    a + 1,
+                    b + 1
+                )
+            }
+            RangeWarning::Adjacent((a, first), (b, second)) => {
+                write!(
+                    f,
+                    "ranges #{} ({first}) and #{} ({second}) are adjacent",
+                    a + 1,
+                    b + 1
+                )
+            }
+        }
+    }
+}
+
+/// Adds `range` to a sorted list of disjoint, non-adjacent ranges, merging as needed.
+fn insert_range(merged: &mut Vec<Range>, range: Range) {
+    let mut range = range.normalized();
+    merged.retain(|other| {
+        if other.overlaps(&range) || other.touches(&range) {
+            range.first = range.first.min(other.first);
+            range.last = range.last.max(other.last);
+            false
+        } else {
+            true
+        }
+    });
+
+    let position = merged.partition_point(|other| other.first < range.first);
+    merged.insert(position, range);
+}
+
+/// Parts of `range` not covered by the sorted, disjoint `covered` ranges.
+fn uncovered_parts(range: &Range, covered: &[Range]) -> Vec<Range> {
+    let mut parts = Vec::new();
+    let mut first = range.first;
+    for other in covered.iter().filter(|other| other.overlaps(range)) {
+        if other.first > first {
+            parts.push(Range {
+                first,
+                last: other.first - 1,
+            });
+        }
+        match other.last.checked_add(1) {
+            Some(next) => first = next,
+            None => return parts, // covered up to u64::MAX
+        }
+    }
+
+    if first <= range.last {
+        parts.push(Range {
+            first,
+            last: range.last,
+        });
+    }
+    parts
+}
+
+/// Merges reversed, overlapping and adjacent ranges so that every id is covered
+/// once, reporting each such case found in the input.
+fn normalize_ranges(ranges: &[Range]) -> (Vec<Range>, Vec<RangeWarning>) {
+    let mut warnings = Vec::new();
+    for (idx, range) in ranges.iter().enumerate() {
+        if range.is_reversed() {
+            warnings.push(RangeWarning::Reversed(idx, *range));
+        }
+    }
+
+    for (a, first) in ranges.iter().enumerate() {
+        for (b, second) in ranges.iter().enumerate().skip(a + 1) {
+            let pair = ((a, *first), (b, *second));
+            let (first, second) = (first.normalized(), second.normalized());
+            if first.overlaps(&second) {
+                warnings.push(RangeWarning::Overlapping(pair.0, pair.1));
+            } else if first.touches(&second) {
+                warnings.push(RangeWarning::Adjacent(pair.0, pair.1));
+            }
+        }
+    }
+
+    let mut merged = Vec::new();
+    for range in ranges {
+        insert_range(&mut merged, *range);
+    }
+
+    (merged, warnings)
+}
+
 fn process_input(input: &str) -> Vec<Range> {
     input
         .split(',')
@@ -27,82 +169,369 @@ fn process_input(input: &str) -> Vec<Range> {
         .collect::<Vec<_>>()
 }
 
-fn check_id_by_part_one_rules(id: i64) -> bool {
-    // convert to decimal string
-    let s = id.to_string();
-    let bytes = s.as_bytes();
-    let len = bytes.len();
+fn scan_invalid_ids<'a>(range: &Range, rule: &'a Rule) -> impl Iterator<Item = u64> + 'a {
+    (range.first..=range.last).filter(move |&id| rule.matches(id))
+}
+
+fn sum_scanned_invalid_ids(ranges: &[Range], rule: &Rule) -> u128 {
+    ranges
+        .iter()
+        .flat_map(|range| scan_invalid_ids(range, rule))
+        .map(u128::from)
+        .sum()
+}
+
+/// Which repeated-block ids count as invalid.
+#[derive(Debug, Copy, Clone)]
+enum Repetition {
+    /// a block repeated exactly twice (part one)
+    Twice,
+    /// a block repeated at least twice (part two)
+    AtLeastTwice,
+}
+
+impl Repetition {
+    /// The equivalent rule, for checking ids one by one.
+    fn rule(&self) -> Rule {
+        match self {
+            Repetition::Twice => Rule::Repeated(2),
+            Repetition::AtLeastTwice => Rule::RepeatedAtLeast(2),
+        }
+    }
+
+    /// The repetition a rule boils down to, if the invalid ids it describes
+    /// can be generated instead of scanned for.
+    fn from_rule(rule: &Rule) -> Option<Repetition> {
+        match rule {
+            Rule::Repeated(2) => Some(Repetition::Twice),
+            Rule::RepeatedAtLeast(2) => Some(Repetition::AtLeastTwice),
+            Rule::Base(10, rule) => Repetition::from_rule(rule),
+            _ => None,
+        }
+    }
+
+    /// Possible repeat counts for ids of `len` digits.
+    fn repeats(&self, len: u32) -> Vec<u32> {
+        match self {
+            Repetition::Twice if len.is_multiple_of(2) => vec![2],
+            Repetition::Twice => Vec::new(),
+            Repetition::AtLeastTwice => (2..=len).filter(|&r| len.is_multiple_of(r)).collect(),
+        }
+    }
+
+    /// `(repeats, sign)` pairs whose signed sum of repeated-block sets of `len`
+    /// digits covers every invalid id of that length exactly once.
+    fn terms(&self, len: u32) -> Vec<(u32, i128)> {
+        match self {
+            Repetition::Twice => self.repeats(len).into_iter().map(|r| (r, 1)).collect(),
+            // an id repeating with period p also repeats with every multiple of p
+            // dividing `len`, so the union over all periods collapses to
+            // inclusion–exclusion over the divisors m of `len`: -Σ μ(m) · f(len / m)
+            Repetition::AtLeastTwice => self
+                .repeats(len)
+                .into_iter()
+                .map(|r| (r, -mobius(r)))
+                .filter(|&(_, sign)| sign != 0)
+                .collect(),
+        }
+    }
+
+    /// Whether `block` is reported as is; part two reports the shortest block,
+    /// so blocks that are themselves repetitive are left to a shorter period.
+    fn accepts_block(&self, block: u64) -> bool {
+        match self {
+            Repetition::Twice => true,
+            Repetition::AtLeastTwice => !Rule::RepeatedAtLeast(2).matches(block),
+        }
+    }
+}
+
+/// Count and sum of a set of invalid ids; signed so that inclusion–exclusion
+/// can subtract over-counted sets.
+#[derive(Debug, Default, Copy, Clone)]
+struct Tally {
+    count: i128,
+    sum: i128,
+}
 
-    // must have even number of digits
-    if len % 2 != 0 {
-        return true;
+impl Tally {
+    fn add_scaled(&mut self, other: Tally, factor: i128) {
+        self.count += factor * other.count;
+        self.sum += factor * other.sum;
     }
+}
+
+// u64::MAX has 20 digits, so no id can be longer than that
+const MAX_DIGITS: u32 = 20;
+
+/// Blocks of `len / repeats` digits (without a leading zero) which, repeated
+/// `repeats` times, land inside `range`.
+///
+/// Such an id equals `block * (10^(block_len * (repeats - 1)) + ... + 10^block_len + 1)`,
+/// so the matching blocks form one contiguous interval and never need to be enumerated.
+/// Returns the multiplier together with the lowest and highest block.
+fn block_interval(range: &Range, len: u32, repeats: u32) -> Option<(u128, u128, u128)> {
+    let base = 10u128.pow(len / repeats);
+    let multiplier = (10u128.pow(len) - 1) / (base - 1);
 
-    // no leading zeros (the puzzle also says inputs don't have them,
-    // but this keeps the function self-contained)
-    if bytes[0] == b'0' {
-        return true;
+    let lowest_block = u128::from(range.first).div_ceil(multiplier).max(base / 10);
+    let highest_block = (u128::from(range.last) / multiplier).min(base - 1);
+    if range.first > range.last || lowest_block > highest_block {
+        return None;
     }
 
-    let mid = len / 2;
-    &bytes[..mid] != &bytes[mid..]
+    Some((multiplier, lowest_block, highest_block))
 }
 
-fn check_id_by_part_two_rules(id: i64) -> bool {
-    let s = id.to_string();
-    let bytes = s.as_bytes();
-    let len = bytes.len();
+fn tally_repeated_blocks(range: &Range, len: u32, repeats: u32) -> Tally {
+    let Some((multiplier, lowest_block, highest_block)) = block_interval(range, len, repeats)
+    else {
+        return Tally::default();
+    };
 
-    // ids are valid if:
-    // - length < 2 (can't be "pattern repeated at least twice")
-    // - or they start with '0' (we treat those as not-invalid per the puzzle)
-    if len < 2 || bytes[0] == b'0' {
-        return true;
+    let count = highest_block - lowest_block + 1;
+    let blocks_sum = (lowest_block + highest_block) * count / 2;
+    Tally {
+        count: count as i128,
+        sum: (blocks_sum * multiplier) as i128,
     }
+}
 
-    // look for a period m such that:
-    // - m divides len
-    // - the number is (first m digits) repeated len/m times
-    for m in 1..=len / 2 {
-        if len % m != 0 {
-            continue;
+fn mobius(mut n: u32) -> i128 {
+    let mut result = 1;
+    let mut factor = 2;
+    while factor * factor <= n {
+        if n.is_multiple_of(factor) {
+            n /= factor;
+            if n.is_multiple_of(factor) {
+                return 0; // squared prime factor
+            }
+            result = -result;
         }
+        factor += 1;
+    }
+
+    if n > 1 { -result } else { result }
+}
 
-        let pattern = &bytes[..m];
-        if bytes.chunks(m).all(|chunk| chunk == pattern) {
-            // found a repeated pattern (at least twice because m <= len/2)
-            return false; // invalid
+fn tally_invalid_ids(range: &Range, repetition: Repetition) -> Tally {
+    let mut tally = Tally::default();
+    for len in 2..=MAX_DIGITS {
+        for (repeats, sign) in repetition.terms(len) {
+            tally.add_scaled(tally_repeated_blocks(range, len, repeats), sign);
         }
     }
+    tally
+}
 
-    // no repeating pattern → valid
-    true
+/// An invalid id along with the block whose repetition makes it invalid.
+#[derive(Debug, Copy, Clone)]
+struct InvalidId {
+    id: u64,
+    block: u64,
+    repeats: u32,
 }
 
-fn count_invalid_ids(range: &Range, function: fn(i64) -> bool) -> Vec<i64> {
-    (range.first..=range.last)
-        .filter(|&id| !function(id)) // still: collect invalid IDs
-        .collect()
+/// Lazily yields the invalid ids of `range` in ascending order.
+fn invalid_ids(range: &Range, repetition: Repetition) -> impl Iterator<Item = InvalidId> {
+    let (first, last) = (range.first, range.last);
+
+    (2..=MAX_DIGITS).flat_map(move |len| {
+        let range = Range { first, last };
+
+        // one ascending source per period; at most one of them yields any given id
+        let mut sources = repetition
+            .repeats(len)
+            .into_iter()
+            .filter_map(|repeats| {
+                let (multiplier, lowest_block, highest_block) =
+                    block_interval(&range, len, repeats)?;
+                let source = (lowest_block..=highest_block)
+                    .map(|block| block as u64)
+                    .filter(move |&block| repetition.accepts_block(block))
+                    .map(move |block| InvalidId {
+                        id: (u128::from(block) * multiplier) as u64,
+                        block,
+                        repeats,
+                    });
+                Some(source.peekable())
+            })
+            .collect::<Vec<_>>();
+
+        // merge the sources by always taking the smallest pending id
+        iter::from_fn(move || {
+            let (_, next) = sources
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(idx, source)| source.peek().map(|invalid| (invalid.id, idx)))
+                .min()?;
+            sources[next].next()
+        })
+    })
+}
+
+#[derive(Debug)]
+struct RangeSummary {
+    count: u64,
+    sum: u128,
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+fn summarize_range(range: &Range, repetition: Repetition) -> RangeSummary {
+    let tally = tally_invalid_ids(range, repetition);
+
+    // every repeated-block id is invalid, whichever period produced it
+    let ends = (2..=MAX_DIGITS)
+        .flat_map(|len| repetition.repeats(len).into_iter().map(move |r| (len, r)))
+        .filter_map(|(len, repeats)| block_interval(range, len, repeats))
+        .map(|(multiplier, lowest, highest)| {
+            ((lowest * multiplier) as u64, (highest * multiplier) as u64)
+        })
+        .collect::<Vec<_>>();
+
+    RangeSummary {
+        count: tally.count as u64,
+        sum: tally.sum as u128,
+        min: ends.iter().map(|&(min, _)| min).min(),
+        max: ends.iter().map(|&(_, max)| max).max(),
+    }
 }
 
-fn process_ids_for_part_one(ranges: &[Range], function: fn(i64) -> bool) -> i64 {
+fn process_ranges(ranges: &[Range], repetition: Repetition) -> u128 {
     ranges
         .iter()
-        .flat_map(|range| count_invalid_ids(range, function))
+        .map(|range| summarize_range(range, repetition).sum)
         .sum()
 }
 
+/// What each original range adds to the total once duplicates are removed;
+/// an id covered by several ranges is credited to the first of them.
+fn range_contributions(ranges: &[Range], repetition: Repetition) -> Vec<Tally> {
+    let mut covered = Vec::new();
+    ranges
+        .iter()
+        .map(|range| {
+            let range = range.normalized();
+            let mut tally = Tally::default();
+            for part in uncovered_parts(&range, &covered) {
+                tally.add_scaled(tally_invalid_ids(&part, repetition), 1);
+            }
+            insert_range(&mut covered, range);
+            tally
+        })
+        .collect()
+}
+
+fn print_contributions(ranges: &[Range], repetition: Repetition) {
+    let contributions = range_contributions(ranges, repetition);
+    for (idx, (range, tally)) in ranges.iter().zip(contributions).enumerate() {
+        let own = tally_invalid_ids(&range.normalized(), repetition);
+        println!(
+            "#{} {range}: count {}, sum {} (count {}, sum {} before deduplication)",
+            idx + 1,
+            tally.count,
+            tally.sum,
+            own.count,
+            own.sum
+        );
+    }
+}
+
+fn print_report(ranges: &[Range], repetition: Repetition, limit: usize) {
+    for range in ranges.iter().map(Range::normalized) {
+        let summary = summarize_range(&range, repetition);
+        let bound = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+        println!(
+            "{}-{}: count {}, sum {}, min {}, max {}",
+            range.first,
+            range.last,
+            summary.count,
+            summary.sum,
+            bound(summary.min),
+            bound(summary.max)
+        );
+
+        for invalid in invalid_ids(&range, repetition).take(limit) {
+            println!(
+                "    {} = {} x {}",
+                invalid.id, invalid.block, invalid.repeats
+            );
+        }
+    }
+}
+
 fn main() -> io::Result<()> {
     let input = include_str!("input.data").trim();
 
-    let ranges = process_input(input);
+    let original_ranges = process_input(input);
+    let (ranges, warnings) = normalize_ranges(&original_ranges);
+    for warning in &warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    // `--scan` checks every id one by one instead of generating the invalid ones,
+    // `--report [N]` summarizes every range and lists up to N of its invalid ids,
+    // `--rule <RULE>` sums the ids invalid under a custom rule (see `Rule`),
+    // `--contributions` shows what every input range adds after deduplication
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let scan = args.iter().any(|arg| arg == "--scan");
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--rule") {
+        let rule = args
+            .get(idx + 1)
+            .expect("Missing rule")
+            .parse::<Rule>()
+            .unwrap_or_else(|err| panic!("Invalid rule: {err}"));
+
+        let result = match Repetition::from_rule(&rule) {
+            Some(repetition) if !scan => process_ranges(&ranges, repetition),
+            _ => sum_scanned_invalid_ids(&ranges, &rule),
+        };
+        println!("The result for rule {rule} is {result}");
+        return Ok(());
+    }
+
+    if scan {
+        println!(
+            "The result for part one is {}",
+            sum_scanned_invalid_ids(&ranges, &Repetition::Twice.rule())
+        );
+        println!(
+            "The result for part two is {}",
+            sum_scanned_invalid_ids(&ranges, &Repetition::AtLeastTwice.rule())
+        );
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--contributions") {
+        println!("Part one:");
+        print_contributions(&original_ranges, Repetition::Twice);
+        println!("Part two:");
+        print_contributions(&original_ranges, Repetition::AtLeastTwice);
+        return Ok(());
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--report") {
+        let limit = args
+            .get(idx + 1)
+            .map(|value| value.parse::<usize>().expect("Invalid listing limit"))
+            .unwrap_or(0);
+
+        println!("Part one:");
+        print_report(&original_ranges, Repetition::Twice, limit);
+        println!("Part two:");
+        print_report(&original_ranges, Repetition::AtLeastTwice, limit);
+        return Ok(());
+    }
+
     println!(
         "The result for part one is {}",
-        process_ids_for_part_one(&ranges, check_id_by_part_one_rules)
+        process_ranges(&ranges, Repetition::Twice)
     );
     println!(
         "The result for part two is {}",
-        process_ids_for_part_one(&ranges, check_id_by_part_two_rules)
+        process_ranges(&ranges, Repetition::AtLeastTwice)
     );
 
     Ok(())
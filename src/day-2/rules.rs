@@ -0,0 +1,339 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(&self, left: u64, right: u64) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+/// A pattern that marks an id as invalid.
+///
+/// Rules are written in a small expression language:
+///
+/// - `repeat(k)` - a block of digits repeated exactly `k` times (k >= 2)
+/// - `repeat_at_least(k)` - a block of digits repeated `k` or more times (k >= 2)
+/// - `palindrome` - the digits read the same both ways
+/// - `run(n)` - contains `n` or more equal digits in a row
+/// - `digit_sum(<op> n)` - the digit sum compared with `n`, `<op>` is one of `< <= = != >= >`
+/// - `base(b, rule)` - evaluates `rule` on the digits in base `b` (2..=36), 10 otherwise
+/// - `!rule`, `rule & rule`, `rule | rule` and parentheses to combine them
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Repeated(u32),
+    RepeatedAtLeast(u32),
+    Palindrome,
+    Run(usize),
+    DigitSum(Comparison, u64),
+    Base(u32, Box<Rule>),
+    Not(Box<Rule>),
+    And(Box<Rule>, Box<Rule>),
+    Or(Box<Rule>, Box<Rule>),
+}
+
+fn digits(mut id: u64, base: u32) -> Vec<u64> {
+    let base = u64::from(base);
+    let mut digits = vec![id % base];
+    while id >= base {
+        id /= base;
+        digits.push(id % base);
+    }
+    digits.reverse();
+    digits
+}
+
+fn is_repeated(digits: &[u64], repeats: usize) -> bool {
+    let len = digits.len();
+    if repeats < 2 || !len.is_multiple_of(repeats) {
+        return false;
+    }
+
+    let pattern = &digits[..len / repeats];
+    digits.chunks(len / repeats).all(|chunk| chunk == pattern)
+}
+
+impl Rule {
+    /// Whether `id` is invalid under this rule.
+    pub fn matches(&self, id: u64) -> bool {
+        self.matches_in_base(id, 10)
+    }
+
+    fn matches_in_base(&self, id: u64, base: u32) -> bool {
+        match self {
+            Rule::Repeated(repeats) => is_repeated(&digits(id, base), *repeats as usize),
+            Rule::RepeatedAtLeast(repeats) => {
+                let digits = digits(id, base);
+                (*repeats as usize..=digits.len()).any(|r| is_repeated(&digits, r))
+            }
+            Rule::Palindrome => {
+                let digits = digits(id, base);
+                digits.iter().eq(digits.iter().rev())
+            }
+            Rule::Run(length) => digits(id, base)
+                .chunk_by(|a, b| a == b)
+                .any(|run| run.len() >= *length),
+            Rule::DigitSum(comparison, value) => {
+                comparison.holds(digits(id, base).iter().sum(), *value)
+            }
+            Rule::Base(base, rule) => rule.matches_in_base(id, *base),
+            Rule::Not(rule) => !rule.matches_in_base(id, base),
+            Rule::And(left, right) => {
+                left.matches_in_base(id, base) && right.matches_in_base(id, base)
+            }
+            Rule::Or(left, right) => {
+                left.matches_in_base(id, base) || right.matches_in_base(id, base)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Repeated(repeats) => write!(f, "repeat({repeats})"),
+            Rule::RepeatedAtLeast(repeats) => write!(f, "repeat_at_least({repeats})"),
+            Rule::Palindrome => write!(f, "palindrome"),
+            Rule::Run(length) => write!(f, "run({length})"),
+            Rule::DigitSum(comparison, value) => {
+                write!(f, "digit_sum({}{value})", comparison.symbol())
+            }
+            Rule::Base(base, rule) => write!(f, "base({base}, {rule})"),
+            Rule::Not(rule) => write!(f, "!{rule}"),
+            Rule::And(left, right) => write!(f, "({left} & {right})"),
+            Rule::Or(left, right) => write!(f, "({left} | {right})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(u64),
+    Comparison(Comparison),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let ch = chars[idx];
+        let next = chars.get(idx + 1).copied();
+        idx += 1;
+
+        let token = match ch {
+            _ if ch.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '=' => Token::Comparison(Comparison::Equal),
+            '!' if next == Some('=') => {
+                idx += 1;
+                Token::Comparison(Comparison::NotEqual)
+            }
+            '!' => Token::Not,
+            '<' | '>' => {
+                let or_equal = next == Some('=');
+                if or_equal {
+                    idx += 1;
+                }
+                Token::Comparison(match (ch, or_equal) {
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessOrEqual,
+                    (_, false) => Comparison::Greater,
+                    (_, true) => Comparison::GreaterOrEqual,
+                })
+            }
+            _ if ch.is_ascii_digit() => {
+                let start = idx - 1;
+                while idx < chars.len() && chars[idx].is_ascii_digit() {
+                    idx += 1;
+                }
+                let number = chars[start..idx].iter().collect::<String>();
+                Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| format!("Number out of range: {number}"))?,
+                )
+            }
+            _ if ch.is_ascii_alphabetic() || ch == '_' => {
+                let start = idx - 1;
+                while idx < chars.len() && (chars[idx].is_ascii_alphanumeric() || chars[idx] == '_')
+                {
+                    idx += 1;
+                }
+                Token::Name(chars[start..idx].iter().collect())
+            }
+            _ => return Err(format!("Unexpected character: '{ch}'")),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the grammar
+///
+/// ```text
+/// or    := and ('|' and)*
+/// and   := unary ('&' unary)*
+/// unary := '!' unary | '(' or ')' | atom
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {expected:?}, found {token:?}")),
+            None => Err(format!("Expected {expected:?}, found end of rule")),
+        }
+    }
+
+    fn number(&mut self) -> Result<u64, String> {
+        match self.advance() {
+            Some(Token::Number(number)) => Ok(number),
+            Some(token) => Err(format!("Expected a number, found {token:?}")),
+            None => Err("Expected a number, found end of rule".to_string()),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Rule, String> {
+        let mut rule = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            rule = Rule::Or(Box::new(rule), Box::new(self.parse_and()?));
+        }
+        Ok(rule)
+    }
+
+    fn parse_and(&mut self) -> Result<Rule, String> {
+        let mut rule = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            rule = Rule::And(Box::new(rule), Box::new(self.parse_unary()?));
+        }
+        Ok(rule)
+    }
+
+    fn parse_unary(&mut self) -> Result<Rule, String> {
+        match self.advance() {
+            Some(Token::Not) => Ok(Rule::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let rule = self.parse_or()?;
+                self.expect(Token::Close)?;
+                Ok(rule)
+            }
+            Some(Token::Name(name)) => self.parse_atom(&name),
+            Some(token) => Err(format!("Unexpected {token:?}")),
+            None => Err("Unexpected end of rule".to_string()),
+        }
+    }
+
+    fn parse_atom(&mut self, name: &str) -> Result<Rule, String> {
+        if name == "palindrome" {
+            return Ok(Rule::Palindrome);
+        }
+
+        self.expect(Token::Open)?;
+        let rule = match name {
+            "repeat" => Rule::Repeated(self.count(2)?),
+            "repeat_at_least" => Rule::RepeatedAtLeast(self.count(2)?),
+            "run" => Rule::Run(self.count(1)? as usize),
+            "digit_sum" => {
+                let comparison = match self.advance() {
+                    Some(Token::Comparison(comparison)) => comparison,
+                    other => return Err(format!("Expected a comparison, found {other:?}")),
+                };
+                Rule::DigitSum(comparison, self.number()?)
+            }
+            "base" => {
+                let base = self.count(2)?;
+                if base > 36 {
+                    return Err(format!("Unsupported base: {base}"));
+                }
+                self.expect(Token::Comma)?;
+                Rule::Base(base, Box::new(self.parse_or()?))
+            }
+            _ => return Err(format!("Unknown rule: {name}")),
+        };
+        self.expect(Token::Close)?;
+
+        Ok(rule)
+    }
+
+    fn count(&mut self, min: u32) -> Result<u32, String> {
+        let number = self.number()?;
+        u32::try_from(number)
+            .ok()
+            .filter(|&count| count >= min)
+            .ok_or_else(|| format!("Expected a number of at least {min}, found {number}"))
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+
+        let rule = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(rule),
+            Some(token) => Err(format!("Unexpected {token:?} after the rule")),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use super::{Bank, Selection};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Objective {
+    #[default]
+    Max,
+    Min,
+}
+
+impl Objective {
+    fn prefers(&self, digit: u8, other: u8) -> bool {
+        match self {
+            Objective::Max => digit > other,
+            Objective::Min => digit < other,
+        }
+    }
+}
+
+/// Restrictions on which batteries may be switched on together.
+///
+/// All of them apply to the whole chosen sequence, including the step from
+/// one segment into the next.
+#[derive(Debug, Default, Clone)]
+pub struct Constraints {
+    pub objective: Objective,
+    /// no two neighbouring batteries may both be on
+    pub no_adjacent: bool,
+    /// at most this many batteries skipped between two chosen ones
+    pub max_gap: Option<usize>,
+    /// total activation cost allowed, see `Bank::costs`
+    pub budget: Option<u64>,
+    /// lengths of consecutive segments to pick `k` batteries from each;
+    /// the whole bank is a single segment when empty
+    pub segments: Vec<usize>,
+}
+
+impl Constraints {
+    /// Whether this is the plain puzzle rule, which `Bank::select_max` solves directly.
+    fn is_plain(&self) -> bool {
+        self.objective == Objective::Max
+            && !self.no_adjacent
+            && self.max_gap.is_none()
+            && self.budget.is_none()
+            && self.segments.is_empty()
+    }
+
+    fn allows_step(&self, from: usize, to: usize) -> bool {
+        to > from
+            && (!self.no_adjacent || to - from >= 2)
+            && self.max_gap.is_none_or(|gap| to - from - 1 <= gap)
+    }
+}
+
+impl Bank {
+    fn cost(&self, idx: usize) -> u64 {
+        self.costs.as_ref().map_or(0, |costs| costs[idx])
+    }
+
+    /// Best selection of `k` batteries (per segment) under `constraints`, or
+    /// `None` if no selection satisfies them.
+    pub fn choose(&self, k: usize, constraints: &Constraints) -> Option<Selection> {
+        if k > self.batteries.len() {
+            return None;
+        }
+
+        if constraints.is_plain() {
+            return Some(self.best_selection(k));
+        }
+
+        self.select_constrained(k, constraints)
+            .map(|positions| self.selection(positions))
+    }
+
+    /// Bounds `[start, end)` of the positions the `step`-th chosen battery may take.
+    fn step_windows(&self, k: usize, constraints: &Constraints) -> Option<Vec<(usize, usize)>> {
+        if constraints.segments.is_empty() {
+            return Some(vec![(0, self.batteries.len()); k]);
+        }
+
+        if constraints.segments.iter().sum::<usize>() != self.batteries.len() {
+            return None;
+        }
+
+        let mut windows = Vec::with_capacity(k * constraints.segments.len());
+        let mut start = 0;
+        for &len in &constraints.segments {
+            windows.extend(std::iter::repeat_n((start, start + len), k));
+            start += len;
+        }
+        Some(windows)
+    }
+
+    /// Picks the digits one by one, keeping every position the best prefix so
+    /// far can end at (with the cheapest way to get there), and only
+    /// considering continuations that can still be completed within budget.
+    ///
+    /// Runs in O(steps · n²), which is fine for banks of puzzle size.
+    fn select_constrained(&self, k: usize, constraints: &Constraints) -> Option<Vec<usize>> {
+        let windows = self.step_windows(k, constraints)?;
+        let steps = windows.len();
+        if steps == 0 {
+            return Some(Vec::new());
+        }
+
+        // completion[step][j]: cheapest cost of choosing j for `step` and
+        // finishing all further steps, None if that cannot be done
+        let n = self.batteries.len();
+        let mut completion = vec![vec![None; n]; steps];
+        for step in (0..steps).rev() {
+            let (start, end) = windows[step];
+            for j in start..end {
+                let rest = match windows.get(step + 1) {
+                    None => Some(0),
+                    Some(&(next_start, next_end)) => (next_start..next_end)
+                        .filter(|&next| constraints.allows_step(j, next))
+                        .filter_map(|next| completion[step + 1][next])
+                        .min(),
+                };
+                completion[step][j] = rest.map(|rest: u64| rest.saturating_add(self.cost(j)));
+            }
+        }
+
+        let budget = constraints.budget.unwrap_or(u64::MAX);
+
+        // frontier of (position, cost spent so far), all sharing the best prefix
+        let mut frontier: Vec<(usize, u64)> = Vec::new();
+        let mut parents = vec![vec![usize::MAX; n]; steps];
+        for (step, &(start, end)) in windows.iter().enumerate() {
+            // cheapest way to reach every candidate from the current frontier
+            let candidates = (start..end)
+                .filter_map(|j| {
+                    let reach = if step == 0 {
+                        Some((usize::MAX, 0))
+                    } else {
+                        frontier
+                            .iter()
+                            .filter(|&&(from, _)| constraints.allows_step(from, j))
+                            .copied()
+                            .min_by_key(|&(_, spent)| spent)
+                    };
+                    let (parent, spent) = reach?;
+                    let total = spent.checked_add(completion[step][j]?)?;
+                    (total <= budget).then(|| (j, parent, spent + self.cost(j)))
+                })
+                .collect::<Vec<_>>();
+
+            let best = candidates
+                .iter()
+                .map(|&(j, _, _)| self.batteries[j])
+                .reduce(|best, digit| {
+                    if constraints.objective.prefers(digit, best) {
+                        digit
+                    } else {
+                        best
+                    }
+                })?;
+
+            frontier.clear();
+            for (j, parent, spent) in candidates {
+                if self.batteries[j] == best {
+                    parents[step][j] = parent;
+                    frontier.push((j, spent));
+                }
+            }
+        }
+
+        // walk the parents back from the cheapest final state
+        let (mut position, _) = frontier.into_iter().min_by_key(|&(_, spent)| spent)?;
+        let mut positions = vec![0; steps];
+        for step in (0..steps).rev() {
+            positions[step] = position;
+            position = parents[step][position];
+        }
+        Some(positions)
+    }
+}
@@ -1,36 +1,172 @@
+use std::env;
+use std::fmt;
+use std::fs;
 use std::io;
-use std::str::FromStr;
+use std::iter::Sum;
+use std::ops::AddAssign;
+use std::time::Instant;
+
+mod constraints;
+
+use constraints::{Constraints, Objective};
 
 #[derive(Debug)]
 struct Bank {
     batteries: Vec<u8>,
+    /// base the battery labels (and so the joltage) are written in
+    radix: u32,
+    /// activation cost of every battery, free when not given
+    costs: Option<Vec<u64>>,
+}
+
+fn digit_char(digit: u8, radix: u32) -> char {
+    char::from_digit(digit as u32, radix)
+        .expect("digit must fit the radix")
+        .to_ascii_uppercase()
+}
+
+/// Non-negative number of any length in the bank's radix, so that joltages of
+/// many batteries neither overflow nor wrap when summed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Joltage {
+    // least significant digit first, no leading zeros (zero is empty)
+    digits: Vec<u8>,
+    radix: u32,
+}
+
+impl Default for Joltage {
+    fn default() -> Self {
+        Joltage {
+            digits: Vec::new(),
+            radix: 10,
+        }
+    }
+}
+
+impl Joltage {
+    fn from_digits(digits: &[u8], radix: u32) -> Self {
+        let mut digits = digits.iter().rev().copied().collect::<Vec<_>>();
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Joltage { digits, radix }
+    }
+}
+
+impl AddAssign<&Joltage> for Joltage {
+    fn add_assign(&mut self, other: &Joltage) {
+        assert_eq!(
+            self.radix, other.radix,
+            "cannot add joltages in different radixes"
+        );
+        if self.digits.len() < other.digits.len() {
+            self.digits.resize(other.digits.len(), 0);
+        }
+
+        let mut carry = 0;
+        for (idx, digit) in self.digits.iter_mut().enumerate() {
+            let sum = *digit + other.digits.get(idx).copied().unwrap_or(0) + carry;
+            *digit = sum % self.radix as u8;
+            carry = sum / self.radix as u8;
+        }
+        if carry > 0 {
+            self.digits.push(carry);
+        }
+    }
+}
+
+impl Sum for Joltage {
+    fn sum<I: Iterator<Item = Joltage>>(iter: I) -> Self {
+        // an empty sum is zero, which looks the same in any radix
+        iter.reduce(|mut total, joltage| {
+            total += &joltage;
+            total
+        })
+        .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Joltage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+        self.digits
+            .iter()
+            .rev()
+            .try_for_each(|&digit| write!(f, "{}", digit_char(digit, self.radix)))
+    }
 }
 
-impl FromStr for Bank {
-    type Err = String;
+/// Batteries switched on in a bank and the joltage they produce.
+#[derive(Debug)]
+struct Selection {
+    positions: Vec<usize>,
+    digits: Vec<u8>,
+    joltage: Joltage,
+}
+
+impl Bank {
+    fn parse(line: &str, radix: u32) -> Result<Self, String> {
+        if !(2..=36).contains(&radix) {
+            return Err(format!("Unsupported radix: {radix}"));
+        }
 
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
         let batteries = line
             .chars()
             .map(|ch| {
-                ch.to_digit(10)
+                ch.to_digit(radix)
                     .ok_or_else(|| format!("Invalid digit: '{}'", ch))
                     .map(|d| d as u8)
             })
             .collect::<Result<Vec<u8>, _>>()?;
 
-        Ok(Bank { batteries })
+        Ok(Bank {
+            batteries,
+            radix,
+            costs: None,
+        })
     }
-}
 
-impl Bank {
-    fn max_joltage(&self, k: usize) -> u64 {
+    /// Positions of the `k` batteries forming the largest joltage.
+    ///
+    /// Keeps a monotonic stack of chosen positions: a smaller digit is dropped
+    /// whenever a bigger one follows and there are still enough batteries left
+    /// to fill all `k` places, so every battery is pushed and popped at most once.
+    fn select_max(&self, k: usize) -> Vec<usize> {
+        let n = self.batteries.len();
+        if k == 0 || k > n {
+            return Vec::new();
+        }
+
+        let mut drops_left = n - k;
+        let mut chosen: Vec<usize> = Vec::with_capacity(n);
+        for (idx, &digit) in self.batteries.iter().enumerate() {
+            while drops_left > 0 {
+                match chosen.last() {
+                    Some(&top) if self.batteries[top] < digit => {
+                        chosen.pop();
+                        drops_left -= 1;
+                    }
+                    _ => break,
+                }
+            }
+            chosen.push(idx);
+        }
+
+        chosen.truncate(k);
+        chosen
+    }
+
+    /// Reference version of `select_max`: rescans the remaining window for
+    /// every chosen digit, which is O(n·k).
+    fn select_max_by_scan(&self, k: usize) -> Vec<usize> {
         let n = self.batteries.len();
         if k == 0 || k > n {
-            return 0;
+            return Vec::new();
         }
 
-        let mut result: u64 = 0;
+        let mut chosen = Vec::with_capacity(k);
         let mut start = 0;
         for remaining in (0..k).rev() {
             // search range end: we must leave `remaining` digits after the chosen one
@@ -48,43 +184,323 @@ impl Bank {
                 }
             }
 
-            // append digit to result
-            result = result * 10 + max_digit as u64;
+            chosen.push(max_pos);
 
             // next search starts after this position
             start = max_pos + 1;
         }
 
-        result
+        chosen
+    }
+
+    fn best_selection(&self, k: usize) -> Selection {
+        self.selection(self.select_max(k))
+    }
+
+    fn selection(&self, positions: Vec<usize>) -> Selection {
+        let digits = positions
+            .iter()
+            .map(|&idx| self.batteries[idx])
+            .collect::<Vec<_>>();
+        let joltage = Joltage::from_digits(&digits, self.radix);
+
+        Selection {
+            positions,
+            digits,
+            joltage,
+        }
+    }
+
+    /// The bank with the selected batteries highlighted: in bold green when
+    /// `color` is set, otherwise with every other battery shown as '.'.
+    fn render(&self, selection: &Selection, color: bool) -> String {
+        let mut chosen = selection.positions.iter().peekable();
+        self.batteries
+            .iter()
+            .enumerate()
+            .map(|(idx, &digit)| {
+                let digit = digit_char(digit, self.radix);
+                let selected = chosen.next_if_eq(&&idx).is_some();
+                match (selected, color) {
+                    (true, true) => format!("\x1b[1;32m{digit}\x1b[0m"),
+                    (false, false) => ".".to_string(),
+                    _ => digit.to_string(),
+                }
+            })
+            .collect()
     }
 }
 
-fn process_banks(banks: &Vec<Bank>, batteries_count: usize) -> u64 {
+fn process_banks(
+    banks: &[Bank],
+    batteries_count: usize,
+    constraints: &Constraints,
+) -> Result<Joltage, String> {
     banks
         .iter()
-        .map(|bank| bank.max_joltage(batteries_count))
+        .enumerate()
+        .map(|(idx, bank)| {
+            bank.choose(batteries_count, constraints)
+                .map(|selection| selection.joltage)
+                .ok_or_else(|| {
+                    format!(
+                        "No valid selection of {batteries_count} batteries in bank #{}",
+                        idx + 1
+                    )
+                })
+        })
         .sum()
 }
 
-fn process_input(input: &str) -> Vec<Bank> {
+/// Reads one line of activation costs per bank, separated by spaces or commas.
+fn load_costs(banks: &mut [Bank], path: &str) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+
+    for (idx, bank) in banks.iter_mut().enumerate() {
+        let line = lines
+            .next()
+            .ok_or_else(|| format!("Missing costs for bank #{}", idx + 1))?;
+        let costs = line
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Invalid costs for bank #{}", idx + 1))?;
+        if costs.len() != bank.batteries.len() {
+            return Err(format!(
+                "Expected {} costs for bank #{}, found {}",
+                bank.batteries.len(),
+                idx + 1,
+                costs.len()
+            ));
+        }
+        bank.costs = Some(costs);
+    }
+
+    Ok(())
+}
+
+fn parse_constraints(args: &[String]) -> Result<Constraints, String> {
+    let value_of = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|idx| args.get(idx + 1).ok_or(format!("Missing value for {flag}")))
+            .transpose()
+    };
+
+    let mut constraints = Constraints::default();
+    if args.iter().any(|arg| arg == "--min") {
+        constraints.objective = Objective::Min;
+    }
+    constraints.no_adjacent = args.iter().any(|arg| arg == "--no-adjacent");
+    if let Some(gap) = value_of("--max-gap")? {
+        constraints.max_gap = Some(gap.parse().map_err(|_| "Invalid maximum gap")?);
+    }
+    if let Some(budget) = value_of("--budget")? {
+        constraints.budget = Some(budget.parse().map_err(|_| "Invalid budget")?);
+    }
+    if let Some(segments) = value_of("--segments")? {
+        constraints.segments = segments
+            .split(',')
+            .map(|len| len.trim().parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| "Invalid segment lengths")?;
+    }
+
+    Ok(constraints)
+}
+
+fn process_input(input: &str, radix: u32) -> Vec<Bank> {
     input
         .split_whitespace()
-        .map(|data| Bank::from_str(data).unwrap())
+        .map(|data| Bank::parse(data, radix).unwrap())
         .collect::<Vec<_>>()
 }
 
+/// Checks the monotonic stack selection against the scanning one for every
+/// bank and every possible `k`.
+fn verify_selection(banks: &[Bank]) -> bool {
+    banks.iter().all(|bank| {
+        (0..=bank.batteries.len() + 1).all(|k| bank.select_max(k) == bank.select_max_by_scan(k))
+    })
+}
+
+/// Bank of `len` pseudo-random digits 1-9 (xorshift, so runs are repeatable).
+fn generate_bank(len: usize, seed: u64) -> Bank {
+    let mut state = seed.max(1);
+    let batteries = (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 9 + 1) as u8
+        })
+        .collect();
+    Bank {
+        batteries,
+        radix: 10,
+        costs: None,
+    }
+}
+
+fn run_benchmark() {
+    let bank = generate_bank(1_000_000, 2025);
+    for k in [2, 12, 1_000] {
+        let started = Instant::now();
+        let fast = bank.select_max(k);
+        let fast_time = started.elapsed();
+
+        let started = Instant::now();
+        let reference = bank.select_max_by_scan(k);
+        let reference_time = started.elapsed();
+
+        assert_eq!(fast, reference, "selections differ for k = {k}");
+        println!("k = {k:>5}: monotonic stack {fast_time:>12.3?}, scan {reference_time:>12.3?}");
+    }
+}
+
+fn print_selections(banks: &[Bank], batteries_count: usize, constraints: &Constraints) {
+    // honour the NO_COLOR convention so the output can be piped into files
+    let color = env::var_os("NO_COLOR").is_none();
+    for bank in banks {
+        let Some(selection) = bank.choose(batteries_count, constraints) else {
+            println!("{}", bank.render(&bank.selection(Vec::new()), color));
+            println!("    no valid selection");
+            continue;
+        };
+        let positions = selection
+            .positions
+            .iter()
+            .map(|idx| idx.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let digits = selection
+            .digits
+            .iter()
+            .map(|&digit| digit_char(digit, bank.radix))
+            .collect::<String>();
+
+        println!("{}", bank.render(&selection, color));
+        println!(
+            "    joltage {} from digits {digits} at positions {positions}",
+            selection.joltage
+        );
+    }
+}
+
 fn main() -> io::Result<()> {
     let input = include_str!("input.data").trim();
 
-    let banks = process_input(input);
+    // `--radix N` reads the battery labels (and prints joltages) in base N
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let radix = match args.iter().position(|arg| arg == "--radix") {
+        Some(idx) => args
+            .get(idx + 1)
+            .and_then(|value| value.parse::<u32>().ok())
+            .expect("Invalid radix"),
+        None => 10,
+    };
+
+    let mut banks = process_input(input, radix);
+
+    // `--verify` cross-checks the selection against the reference scan,
+    // `--bench` times both on a bank of a million batteries,
+    // `--show [K]` prints every bank with its K (12 by default) chosen batteries,
+    // `--batteries K` sums the joltages for any K up to the bank length;
+    // the selection can be changed with `--min`, `--no-adjacent`, `--max-gap N`,
+    // `--costs FILE` with `--budget N`, and `--segments LEN,LEN,...`
+    let constraints = parse_constraints(&args).map_err(io::Error::other)?;
+    if let Some(idx) = args.iter().position(|arg| arg == "--costs") {
+        let path = args.get(idx + 1).expect("Missing costs file");
+        load_costs(&mut banks, path).map_err(io::Error::other)?;
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--batteries") {
+        let batteries_count = args
+            .get(idx + 1)
+            .and_then(|value| value.parse::<usize>().ok())
+            .expect("Invalid batteries count");
+        println!(
+            "The answer for {batteries_count} batteries is : {}",
+            process_banks(&banks, batteries_count, &constraints).map_err(io::Error::other)?
+        );
+        return Ok(());
+    }
+
+    if let Some(idx) = args.iter().position(|arg| arg == "--show") {
+        let batteries_count = args
+            .get(idx + 1)
+            .map(|value| value.parse::<usize>().expect("Invalid batteries count"))
+            .unwrap_or(12);
+        print_selections(&banks, batteries_count, &constraints);
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--verify") {
+        let verified = verify_selection(&banks);
+        println!("Selections match the reference: {verified}");
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--bench") {
+        run_benchmark();
+        return Ok(());
+    }
+
     println!(
-        "The answer for part one is : {:?}",
-        process_banks(&banks, 2)
+        "The answer for part one is : {}",
+        process_banks(&banks, 2, &constraints).map_err(io::Error::other)?
     );
     println!(
-        "The answer for part two is : {:?}",
-        process_banks(&banks, 12)
+        "The answer for part two is : {}",
+        process_banks(&banks, 12, &constraints).map_err(io::Error::other)?
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_matches_scan(bank: &Bank) {
+        for k in 0..=bank.batteries.len() + 1 {
+            assert_eq!(
+                bank.select_max(k),
+                bank.select_max_by_scan(k),
+                "k = {k} on {:?}",
+                bank.batteries
+            );
+        }
+    }
+
+    #[test]
+    fn select_max_matches_scan_on_sample_banks() {
+        for line in include_str!("test.data").lines() {
+            assert_matches_scan(&Bank::parse(line, 10).unwrap());
+        }
+    }
+
+    #[test]
+    fn select_max_matches_scan_on_generated_banks() {
+        for len in 0..40 {
+            for seed in 1..=25 {
+                assert_matches_scan(&generate_bank(len, seed * 7919 + len as u64));
+            }
+        }
+    }
+
+    #[test]
+    fn choose_refuses_more_batteries_than_the_bank_holds() {
+        let bank = Bank::parse("987654321111111", 10).unwrap();
+        let min = Constraints {
+            objective: Objective::Min,
+            ..Constraints::default()
+        };
+        for constraints in [Constraints::default(), min] {
+            assert!(bank.choose(15, &constraints).is_some());
+            assert!(bank.choose(16, &constraints).is_none());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{AccessRule, Grid, Topology};
+
+/// Life-like rule: an empty cell gets a roll when its neighbour count is in
+/// `birth`, a roll stays when its count is in `survival`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifeRule {
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+}
+
+impl LifeRule {
+    /// Day 4's removal process: nothing appears, and a roll stays only while
+    /// it has at least `threshold` neighbours.
+    pub fn removal(rule: &AccessRule) -> Self {
+        LifeRule {
+            birth: Vec::new(),
+            survival: (rule.threshold..=rule.offsets.len()).collect(),
+        }
+    }
+}
+
+impl FromStr for LifeRule {
+    type Err = String;
+
+    /// Parses `B<counts>/S<counts>` such as `B3/S23`; the counts are single
+    /// digits, or comma separated (`B3/S2,3,10`) for bigger neighbourhoods.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let counts = |part: &str, prefix: char| -> Result<Vec<usize>, String> {
+            let digits = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or_else(|| format!("Expected '{prefix}' in: {part}"))?;
+
+            let parse = |count: &str| {
+                count
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid neighbour count: {count}"))
+            };
+            if digits.contains(',') {
+                digits.split(',').map(parse).collect()
+            } else {
+                digits
+                    .chars()
+                    .map(|digit| parse(&digit.to_string()))
+                    .collect()
+            }
+        };
+
+        let (birth, survival) = input
+            .trim()
+            .split_once('/')
+            .ok_or("Expected B<counts>/S<counts>")?;
+        Ok(LifeRule {
+            birth: counts(birth, 'B')?,
+            survival: counts(survival, 'S')?,
+        })
+    }
+}
+
+impl fmt::Display for LifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |counts: &[usize]| {
+            let separator = if counts.iter().any(|&count| count > 9) {
+                ","
+            } else {
+                ""
+            };
+            counts
+                .iter()
+                .map(|count| count.to_string())
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+/// How a run of the automaton ended.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Outcome {
+    /// nothing changes any more from this generation on
+    Stable(usize),
+    /// the generation `start + period` repeats the generation `start`
+    Cycle { start: usize, period: usize },
+    /// the generation limit was reached first
+    Limit(usize),
+}
+
+/// Cellular automaton over a day 4 grid, where rolls ('@') are the live cells,
+/// using the neighbourhood of an `AccessRule` and the edges of a `Topology`.
+pub struct Automaton<'a> {
+    grid: Grid,
+    life: LifeRule,
+    neighbourhood: &'a AccessRule,
+    topology: Topology,
+    generation: usize,
+}
+
+impl<'a> Automaton<'a> {
+    pub fn new(
+        grid: &Grid,
+        life: LifeRule,
+        neighbourhood: &'a AccessRule,
+        topology: Topology,
+    ) -> Self {
+        Automaton {
+            grid: grid.clone(),
+            life,
+            neighbourhood,
+            topology,
+            generation: 0,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Advances one generation, returning whether any cell changed; a grid
+    /// that no longer changes stays at its generation.
+    pub fn step(&mut self) -> bool {
+        let mut next = self.grid.clone();
+        let mut changed = false;
+
+        for row in 0..self.grid.height() {
+            for col in 0..self.grid.width() {
+                let alive = self.grid.data[row][col] == '@';
+                let neighbors =
+                    self.grid
+                        .count_neighbor_rolls(row, col, self.neighbourhood, self.topology);
+
+                let counts = if alive {
+                    &self.life.survival
+                } else {
+                    &self.life.birth
+                };
+                let alive_next = counts.contains(&neighbors);
+                if alive_next != alive {
+                    next.data[row][col] = if alive_next { '@' } else { '.' };
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.grid = next;
+            self.generation += 1;
+        }
+        changed
+    }
+
+    /// Steps until the grid stops changing, repeats an earlier generation or
+    /// `limit` generations have passed, calling `on_generation` for the
+    /// starting grid and after every step.
+    pub fn run(&mut self, limit: usize, mut on_generation: impl FnMut(usize, &Grid)) -> Outcome {
+        let mut seen = HashMap::new();
+        on_generation(self.generation, &self.grid);
+
+        loop {
+            if let Some(&start) = seen.get(&self.grid.data) {
+                return Outcome::Cycle {
+                    start,
+                    period: self.generation - start,
+                };
+            }
+            seen.insert(self.grid.data.clone(), self.generation);
+
+            if self.generation >= limit {
+                return Outcome::Limit(self.generation);
+            }
+
+            if !self.step() {
+                return Outcome::Stable(self.generation);
+            }
+            on_generation(self.generation, &self.grid);
+        }
+    }
+}
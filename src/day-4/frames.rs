@@ -0,0 +1,76 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use super::Grid;
+
+// rolls about to go in the next round are marked with this in the frames
+pub const NEXT_TO_GO: char = 'x';
+
+/// Writes every frame as `frame-NNNN.txt` into `dir`, creating it if needed.
+pub fn write_text_frames(frames: &[Grid], dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (idx, frame) in frames.iter().enumerate() {
+        fs::write(
+            dir.join(format!("frame-{idx:04}.txt")),
+            format!("{frame}\n"),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes every frame as a binary PPM image `frame-NNNN.ppm` into `dir`,
+/// each cell drawn as a `scale`×`scale` square.
+pub fn write_ppm_frames(frames: &[Grid], dir: &Path, scale: usize) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (idx, frame) in frames.iter().enumerate() {
+        let width = frame.width() * scale;
+        let height = frame.height() * scale;
+
+        let mut image = format!("P6\n{width} {height}\n255\n").into_bytes();
+        image.reserve(width * height * 3);
+        for row in &frame.data {
+            let pixels = row
+                .iter()
+                .flat_map(|&cell| {
+                    let color: [u8; 3] = match cell {
+                        '@' => [40, 40, 40],
+                        NEXT_TO_GO => [220, 40, 40],
+                        _ => [245, 245, 245],
+                    };
+                    std::iter::repeat_n(color, scale).flatten()
+                })
+                .collect::<Vec<_>>();
+            for _ in 0..scale {
+                image.extend_from_slice(&pixels);
+            }
+        }
+
+        fs::write(dir.join(format!("frame-{idx:04}.ppm")), image)?;
+    }
+    Ok(())
+}
+
+/// Plays the frames in the terminal, redrawing in place every `delay`.
+pub fn animate(frames: &[Grid], delay: Duration, out: &mut impl Write) -> io::Result<()> {
+    for (idx, frame) in frames.iter().enumerate() {
+        // clear the screen and move the cursor to the top left corner
+        write!(out, "\x1b[2J\x1b[H")?;
+        for row in &frame.data {
+            for &cell in row {
+                match cell {
+                    NEXT_TO_GO => write!(out, "\x1b[1;31m@\x1b[0m")?,
+                    cell => write!(out, "{cell}")?,
+                }
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "After {idx} of {} rounds", frames.len() - 1)?;
+        out.flush()?;
+
+        thread::sleep(delay);
+    }
+    Ok(())
+}
@@ -1,20 +1,157 @@
 use std::collections::VecDeque;
+use std::env;
+use std::fmt;
 use std::io;
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
-// directions for 8 neighbors
-static DIRECTIONS: &[(isize, isize)] = &[
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, -1),
-    (1, 0),
-    (1, 1),
-];
+mod automaton;
+mod frames;
 
-#[derive(Debug)]
+use automaton::{Automaton, LifeRule, Outcome};
+
+/// Shape of the area around a roll whose rolls count as its neighbours.
+#[derive(Debug, Clone, PartialEq)]
+enum Neighbourhood {
+    /// the square of the given radius (8 neighbours for radius 1)
+    Moore(usize),
+    /// the diamond of the given radius (4 neighbours for radius 1)
+    VonNeumann(usize),
+    /// the hexagon of the given radius in axial coordinates, i.e. each row is
+    /// shifted half a cell to the left of the one below (6 neighbours for radius 1)
+    Hexagonal(usize),
+    /// explicit `(row, col)` shifts
+    Custom(Vec<(isize, isize)>),
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    /// Parses `moore`, `von-neumann` and `hex` with an optional `:radius`,
+    /// or `custom:row,col;row,col;...`.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (name, parameter) = match input.split_once(':') {
+            Some((name, parameter)) => (name, Some(parameter)),
+            None => (input, None),
+        };
+
+        let radius = || -> Result<usize, String> {
+            parameter.map_or(Ok(1), |radius| {
+                radius
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid radius: {radius}"))
+            })
+        };
+
+        match name.trim() {
+            "moore" => Ok(Neighbourhood::Moore(radius()?)),
+            "von-neumann" => Ok(Neighbourhood::VonNeumann(radius()?)),
+            "hex" => Ok(Neighbourhood::Hexagonal(radius()?)),
+            "custom" => parameter
+                .ok_or("Missing custom offsets")?
+                .split(';')
+                .map(|offset| {
+                    let (row, col) = offset
+                        .split_once(',')
+                        .ok_or_else(|| format!("Invalid offset: {offset}"))?;
+                    let row = row.trim().parse::<isize>();
+                    let col = col.trim().parse::<isize>();
+                    match (row, col) {
+                        (Ok(row), Ok(col)) => Ok((row, col)),
+                        _ => Err(format!("Invalid offset: {offset}")),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Neighbourhood::Custom),
+            other => Err(format!("Unknown neighbourhood: {other}")),
+        }
+    }
+}
+
+impl Neighbourhood {
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        let square = |radius: usize| {
+            let radius = radius as isize;
+            (-radius..=radius)
+                .flat_map(move |row| (-radius..=radius).map(move |col| (row, col)))
+                .filter(|&offset| offset != (0, 0))
+        };
+
+        match self {
+            Neighbourhood::Moore(radius) => square(*radius).collect(),
+            Neighbourhood::VonNeumann(radius) => square(*radius)
+                .filter(|(row, col)| row.unsigned_abs() + col.unsigned_abs() <= *radius)
+                .collect(),
+            Neighbourhood::Hexagonal(radius) => square(*radius)
+                .filter(|(row, col)| (row + col).unsigned_abs() <= *radius)
+                .collect(),
+            Neighbourhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+}
+
+/// When a roll is accessible: fewer than `threshold` of its neighbours are rolls.
+#[derive(Debug, Clone)]
+struct AccessRule {
+    offsets: Vec<(isize, isize)>,
+    threshold: usize,
+}
+
+impl AccessRule {
+    fn new(neighbourhood: &Neighbourhood, threshold: usize) -> Self {
+        AccessRule {
+            offsets: neighbourhood.offsets(),
+            threshold,
+        }
+    }
+}
+
+impl Default for AccessRule {
+    /// The puzzle rule: fewer than four rolls among the eight adjacent cells.
+    fn default() -> Self {
+        AccessRule::new(&Neighbourhood::Moore(1), 4)
+    }
+}
+
+/// What lies beyond the edges of the grid.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+enum Topology {
+    /// nothing, cells outside are empty
+    #[default]
+    Bounded,
+    /// solid walls, cells outside count as rolls that never go away
+    Walls,
+    /// the grid wraps around into a torus
+    Torus,
+    /// the edges reflect back into the grid, e.g. column -1 is column 0
+    Mirror,
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "bounded" => Ok(Topology::Bounded),
+            "walls" => Ok(Topology::Walls),
+            "torus" => Ok(Topology::Torus),
+            "mirror" => Ok(Topology::Mirror),
+            other => Err(format!("Unknown topology: {other}")),
+        }
+    }
+}
+
+/// Where a shifted position ends up under a topology.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Cell {
+    Inside(usize, usize),
+    Wall,
+    Outside,
+}
+
+#[derive(Debug, Clone)]
 struct Grid {
     data: Vec<Vec<char>>,
 }
@@ -28,7 +165,26 @@ impl FromStr for Grid {
     }
 }
 
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self
+            .data
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
 impl Grid {
+    fn count_rolls(&self) -> usize {
+        self.data
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell == '@')
+            .count()
+    }
+
     fn width(&self) -> usize {
         self.data[0].len()
     }
@@ -40,95 +196,194 @@ impl Grid {
     fn validate_position(&self, row: isize, col: isize) -> bool {
         0 <= row && row < self.height() as isize && 0 <= col && col < self.width() as isize
     }
+
+    fn resolve(&self, row: isize, col: isize, topology: Topology) -> Cell {
+        let reflect = |position: isize, size: usize| {
+            let size = size as isize;
+            let position = position.rem_euclid(2 * size);
+            if position < size {
+                position
+            } else {
+                2 * size - 1 - position
+            }
+        };
+
+        match topology {
+            _ if self.validate_position(row, col) => Cell::Inside(row as usize, col as usize),
+            Topology::Bounded => Cell::Outside,
+            Topology::Walls => Cell::Wall,
+            Topology::Torus => Cell::Inside(
+                row.rem_euclid(self.height() as isize) as usize,
+                col.rem_euclid(self.width() as isize) as usize,
+            ),
+            Topology::Mirror => Cell::Inside(
+                reflect(row, self.height()) as usize,
+                reflect(col, self.width()) as usize,
+            ),
+        }
+    }
+
+    /// Cells around `(row, col)` under `rule`, one per offset, so a cell
+    /// reached by several offsets (possible on small wrapped grids) repeats.
+    fn neighbors(
+        &self,
+        row: usize,
+        col: usize,
+        rule: &AccessRule,
+        topology: Topology,
+    ) -> impl Iterator<Item = Cell> {
+        rule.offsets.iter().map(move |(row_shift, col_shift)| {
+            self.resolve(row as isize + row_shift, col as isize + col_shift, topology)
+        })
+    }
+
+    fn count_neighbor_rolls(
+        &self,
+        row: usize,
+        col: usize,
+        rule: &AccessRule,
+        topology: Topology,
+    ) -> usize {
+        self.neighbors(row, col, rule, topology)
+            .filter(|cell| match *cell {
+                Cell::Inside(row, col) => self.data[row][col] == '@',
+                Cell::Wall => true,
+                Cell::Outside => false,
+            })
+            .count()
+    }
 }
 
-fn count_accessible_paper(grid: &Grid) -> usize {
+fn count_accessible_paper(grid: &Grid, rule: &AccessRule, topology: Topology) -> usize {
     let mut accessible_count = 0usize;
 
-    for row in 0..grid.height() as isize {
-        for col in 0..grid.width() as isize {
-            if grid.data[row as usize][col as usize] != '@' {
+    for row in 0..grid.height() {
+        for col in 0..grid.width() {
+            if grid.data[row][col] != '@' {
                 continue;
             }
 
-            let mut neighbor_rolls = 0;
+            if grid.count_neighbor_rolls(row, col, rule, topology) < rule.threshold {
+                accessible_count += 1;
+            }
+        }
+    }
 
-            for (row_shift, col_shift) in DIRECTIONS {
-                let next_row = row + row_shift;
-                let next_col = col + col_shift;
+    accessible_count
+}
+
+/// Outcome of peeling the rolls off wave by wave: a roll goes in round `r + 1`
+/// once all rolls of rounds up to `r` are gone and it becomes accessible.
+#[derive(Debug)]
+struct RemovalHistory {
+    /// round (starting from 1) in which each roll is removed, `None` for
+    /// empty cells and rolls that are never accessible
+    rounds: Vec<Vec<Option<usize>>>,
+    /// number of rolls removed in each round
+    round_sizes: Vec<usize>,
+}
 
-                if grid.validate_position(next_row, next_col) {
-                    if grid.data[next_row as usize][next_col as usize] == '@' {
-                        neighbor_rolls += 1;
+impl RemovalHistory {
+    fn removed(&self) -> usize {
+        self.round_sizes.iter().sum()
+    }
+
+    /// The grid before the first round and after every round, with the rolls
+    /// going in the next round marked as `frames::NEXT_TO_GO`.
+    fn frames(&self, grid: &Grid) -> Vec<Grid> {
+        (0..=self.round_sizes.len())
+            .map(|done| {
+                let mut frame = grid.clone();
+                for (row, rounds) in frame.data.iter_mut().zip(&self.rounds) {
+                    for (cell, round) in row.iter_mut().zip(rounds) {
+                        match *round {
+                            Some(round) if round <= done => *cell = '.',
+                            Some(round) if round == done + 1 => *cell = frames::NEXT_TO_GO,
+                            _ => {}
+                        }
                     }
                 }
-            }
+                frame
+            })
+            .collect()
+    }
 
-            if neighbor_rolls < 4 {
-                accessible_count += 1;
-            }
-        }
+    /// The grid with every removed roll replaced by its round number
+    /// (1-9, then a-z, '+' beyond that); remaining rolls stay '@'.
+    fn render(&self, grid: &Grid) -> String {
+        grid.data
+            .iter()
+            .zip(&self.rounds)
+            .map(|(row, rounds)| {
+                row.iter()
+                    .zip(rounds)
+                    .map(|(&cell, round)| match round {
+                        Some(round) => char::from_digit(*round as u32, 36).unwrap_or('+'),
+                        None => cell,
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
+}
 
-    accessible_count
+fn count_removable_paper(grid: &Grid, rule: &AccessRule, topology: Topology) -> usize {
+    peel_paper(grid, rule, topology).removed()
 }
 
-fn count_removable_paper(grid: &Grid) -> usize {
+fn peel_paper(grid: &Grid, rule: &AccessRule, topology: Topology) -> RemovalHistory {
     let grid_height = grid.height();
     let grid_width = grid.width();
 
     // present[r][c] == true iff there is still a roll there (not yet removed)
     let mut present = vec![vec![false; grid_width]; grid_height];
-    // degree[r][c] = number of neighboring rolls
-    let mut degree = vec![vec![0u8; grid_width]; grid_height];
+    // degree[r][c] = number of neighboring rolls (walls included)
+    let mut degree = vec![vec![0usize; grid_width]; grid_height];
+    // dependents[r][c] = rolls that count (r, c) as a neighbour; with asymmetric
+    // offsets or mirrored edges these are not simply the neighbours of (r, c)
+    let mut dependents = vec![vec![Vec::new(); grid_width]; grid_height];
     for row in 0..grid_height {
         for col in 0..grid_width {
             if grid.data[row][col] == '@' {
                 present[row][col] = true;
+                degree[row][col] = grid.count_neighbor_rolls(row, col, rule, topology);
 
-                let mut neighbors = 0u8;
-                for (row_shift, col_shift) in DIRECTIONS {
-                    let next_row = row as isize + row_shift;
-                    let next_col = col as isize + col_shift;
-
-                    if grid.validate_position(next_row, next_col) {
-                        if grid.data[next_row as usize][next_col as usize] == '@' {
-                            neighbors += 1;
-                        }
+                for cell in grid.neighbors(row, col, rule, topology) {
+                    if let Cell::Inside(next_row, next_col) = cell {
+                        dependents[next_row][next_col].push((row, col));
                     }
                 }
-                degree[row][col] = neighbors;
             }
         }
     }
 
     let mut queue = VecDeque::new();
+    let mut rounds = vec![vec![None; grid_width]; grid_height];
 
-    // initially, any roll with degree < 4 is removable
+    // initially, any roll with degree below the threshold is removable
     for row in 0..grid_height {
         for col in 0..grid_width {
-            if present[row][col] && degree[row][col] < 4 {
+            if present[row][col] && degree[row][col] < rule.threshold {
                 present[row][col] = false;
+                rounds[row][col] = Some(1);
                 queue.push_back((row, col));
             }
         }
     }
 
-    let mut removed = 0usize;
+    // the queue holds whole rounds one after another, so a roll freed while
+    // removing round `r` is only picked up in round `r + 1`
+    let mut round_sizes = Vec::new();
     while let Some((row, col)) = queue.pop_front() {
-        removed += 1;
-
-        // removing this roll reduces degree of its neighbors
-        for (row_shift, col_shift) in DIRECTIONS {
-            let next_row = row as isize + row_shift;
-            let next_col = col as isize + col_shift;
-
-            if !grid.validate_position(next_row, next_col) {
-                continue;
-            }
+        let round = rounds[row][col].expect("queued rolls have a round");
+        if round_sizes.len() < round {
+            round_sizes.push(0);
+        }
+        round_sizes[round - 1] += 1;
 
-            let next_row = next_row as usize;
-            let next_col = next_col as usize;
+        // removing this roll reduces degree of the rolls that count it as a neighbour
+        for &(next_row, next_col) in &dependents[row][col] {
             if !present[next_row][next_col] {
                 continue;
             }
@@ -137,27 +392,143 @@ fn count_removable_paper(grid: &Grid) -> usize {
                 degree[next_row][next_col] -= 1;
             }
 
-            if degree[next_row][next_col] < 4 {
+            if degree[next_row][next_col] < rule.threshold {
                 present[next_row][next_col] = false;
+                rounds[next_row][next_col] = Some(round + 1);
                 queue.push_back((next_row, next_col));
             }
         }
     }
 
-    removed
+    RemovalHistory {
+        rounds,
+        round_sizes,
+    }
 }
 
 fn main() -> io::Result<()> {
     let input = include_str!("input.data").trim();
 
     let grid = Grid::from_str(input).unwrap();
+
+    // `--neighbourhood SPEC` and `--threshold N` change the accessibility rule,
+    // see `Neighbourhood` for the accepted shapes; `--topology bounded|walls|torus|mirror`
+    // picks what lies beyond the edges
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let value_of = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|idx| args.get(idx + 1).expect("Missing flag value"))
+    };
+    let neighbourhood = value_of("--neighbourhood")
+        .map(|spec| {
+            spec.parse::<Neighbourhood>()
+                .unwrap_or_else(|err| panic!("{err}"))
+        })
+        .unwrap_or(Neighbourhood::Moore(1));
+    let threshold = value_of("--threshold")
+        .map(|value| value.parse::<usize>().expect("Invalid threshold"))
+        .unwrap_or(AccessRule::default().threshold);
+    let rule = AccessRule::new(&neighbourhood, threshold);
+    let topology = value_of("--topology")
+        .map(|name| {
+            name.parse::<Topology>()
+                .unwrap_or_else(|err| panic!("{err}"))
+        })
+        .unwrap_or_default();
+
+    // `--layers` shows the round in which every roll goes and how many go per round
+    if args.iter().any(|arg| arg == "--layers") {
+        let history = peel_paper(&grid, &rule, topology);
+        println!("{}", history.render(&grid));
+        for (round, size) in history.round_sizes.iter().enumerate() {
+            println!("Round {}: {size} rolls", round + 1);
+        }
+        println!(
+            "{} rolls removed in {} forklift passes",
+            history.removed(),
+            history.round_sizes.len()
+        );
+        return Ok(());
+    }
+
+    // `--frames DIR` and `--ppm DIR [--scale N]` save the grid after every round
+    // as text or image files, `--animate [MS]` plays the rounds in the terminal
+    let frames_dir = value_of("--frames");
+    let ppm_dir = value_of("--ppm");
+    let animate = args.iter().position(|arg| arg == "--animate");
+    if frames_dir.is_some() || ppm_dir.is_some() || animate.is_some() {
+        let frames = peel_paper(&grid, &rule, topology).frames(&grid);
+
+        if let Some(dir) = frames_dir {
+            frames::write_text_frames(&frames, Path::new(dir))?;
+        }
+        if let Some(dir) = ppm_dir {
+            let scale = value_of("--scale")
+                .map(|value| value.parse::<usize>().expect("Invalid scale"))
+                .unwrap_or(4);
+            assert!(scale >= 1, "Scale must be at least 1");
+            frames::write_ppm_frames(&frames, Path::new(dir), scale)?;
+        }
+        if let Some(idx) = animate {
+            let delay = args
+                .get(idx + 1)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(200);
+            frames::animate(
+                &frames,
+                Duration::from_millis(delay),
+                &mut io::stdout().lock(),
+            )?;
+        }
+
+        println!("{} frames", frames.len());
+        return Ok(());
+    }
+
+    // `--life B3/S23|removal` runs a cellular automaton (day 4's own removal
+    // rule by default) for at most `--generations N` steps, printing every
+    // generation with `--export`
+    if let Some(spec) = value_of("--life") {
+        let life = match spec.as_str() {
+            "removal" => LifeRule::removal(&rule),
+            spec => spec.parse().unwrap_or_else(|err| panic!("{err}")),
+        };
+        let limit = value_of("--generations")
+            .map(|value| value.parse::<usize>().expect("Invalid generations count"))
+            .unwrap_or(1000);
+        let export = args.iter().any(|arg| arg == "--export");
+
+        println!("Running {life}");
+        let mut automaton = Automaton::new(&grid, life, &rule, topology);
+        let outcome = automaton.run(limit, |generation, grid| {
+            if export {
+                println!("Generation {generation}\n{grid}\n");
+            }
+        });
+
+        match outcome {
+            Outcome::Stable(generation) => println!("Stable from generation {generation}"),
+            Outcome::Cycle { start, period } => {
+                println!("Cycle of period {period} from generation {start}")
+            }
+            Outcome::Limit(generation) => println!("Still changing at generation {generation}"),
+        }
+        println!(
+            "{} rolls left after generation {}",
+            automaton.grid().count_rolls(),
+            automaton.generation()
+        );
+        return Ok(());
+    }
+
     println!(
         "The answer for part one is {}",
-        count_accessible_paper(&grid)
+        count_accessible_paper(&grid, &rule, topology)
     );
     println!(
         "The answer for part two is {}",
-        count_removable_paper(&grid)
+        count_removable_paper(&grid, &rule, topology)
     );
 
     Ok(())
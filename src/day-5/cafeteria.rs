@@ -1,7 +1,20 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
 use std::io;
+use std::ops::Bound;
 use std::str::FromStr;
 
-#[derive(Debug)]
+mod edits;
+mod ingredients;
+mod interval_tree;
+
+use edits::Edit;
+use ingredients::Ingredients;
+use interval_tree::{IntervalTree, Origin, Source};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 struct Range {
     first: u64,
     last: u64,
@@ -15,14 +28,25 @@ impl FromStr for Range {
 
         let first = first.trim().parse::<u64>().map_err(|_| "Bad first")?;
         let last = last.trim().parse::<u64>().map_err(|_| "Bad last")?;
+        if first > last {
+            return Err("Range starts after it ends");
+        }
 
         Ok(Range { first, last })
     }
 }
 
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.first, self.last)
+    }
+}
+
 impl Range {
-    fn len(&self) -> u64 {
-        self.last - self.first + 1
+    /// Number of ids in the range; a u128 since `0-18446744073709551615`
+    /// holds one more than `u64::MAX`.
+    fn len(&self) -> u128 {
+        u128::from(self.last - self.first) + 1
     }
 
     fn check(&self, number: u64) -> bool {
@@ -32,8 +56,17 @@ impl Range {
 
 #[derive(Debug)]
 struct Database {
-    ranges: Vec<Range>,
-    ingredients: Vec<u64>,
+    // merged view used by the freshness queries, keyed by range start
+    ranges: BTreeMap<u64, u64>,
+    // the original ranges, to tell which of them cover an id
+    sources: IntervalTree,
+    // every ingredient id with the number of times it was listed
+    ingredients: Ingredients,
+    // running counts, kept up to date by the edits
+    fresh_ids: u128,
+    fresh_ingredients: usize,
+    // whether touching ranges such as `3-5` and `6-8` are merged too
+    merge_adjacent: bool,
 }
 
 impl FromStr for Database {
@@ -45,10 +78,17 @@ impl FromStr for Database {
             .or_else(|| input.split_once("\r\n\r\n"))
             .ok_or("Could not parse delimiter")?;
 
-        // parse ranges
-        let ranges = ranges
+        // parse ranges, remembering their line numbers
+        let sources = ranges
             .lines()
-            .map(|line| line.parse::<Range>())
+            .enumerate()
+            .map(|(idx, line)| {
+                line.parse::<Range>().map(|range| Source {
+                    range,
+                    origin: Origin::Input,
+                    line: idx + 1,
+                })
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         // parse ingredients
@@ -58,18 +98,18 @@ impl FromStr for Database {
             .map(|line| line.trim().parse::<u64>().map_err(|_| "Invalid integer"))
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(Database::new(ranges, ingredients))
+        Ok(Database::new(sources, ingredients))
     }
 }
 
 impl Database {
-    fn new(ranges: Vec<Range>, ingredients: Vec<u64>) -> Self {
+    fn new(sources: Vec<Source>, ingredients: Vec<u64>) -> Self {
         // sort ranges by start (and then by end for stability)
-        let mut ranges = ranges;
-        ranges.sort_by(|a, b| match a.first.cmp(&b.first) {
-            std::cmp::Ordering::Equal => a.last.cmp(&b.last),
-            other => other,
-        });
+        let mut ranges = sources
+            .iter()
+            .map(|source| source.range)
+            .collect::<Vec<_>>();
+        ranges.sort();
 
         // merge overlapping ranges
         let mut merged: Vec<Range> = Vec::new();
@@ -91,31 +131,317 @@ impl Database {
             }
         }
 
-        Self {
-            ranges: merged,
-            ingredients,
+        let mut database = Self {
+            ranges: merged
+                .iter()
+                .map(|range| (range.first, range.last))
+                .collect(),
+            sources: IntervalTree::new(sources),
+            ingredients: Ingredients::new(),
+            fresh_ids: merged.iter().map(|range| range.len()).sum(),
+            fresh_ingredients: 0,
+            merge_adjacent: false,
+        };
+        for value in ingredients {
+            database.add_ingredient(value);
         }
+        database
     }
 
-    fn count_fresh_ingredients(&self) -> usize {
-        self.ingredients
+    /// Merges touching ranges from now on, joining those already there.
+    fn merge_adjacent(&mut self) {
+        self.merge_adjacent = true;
+
+        let mut merged: Vec<Range> = Vec::new();
+        for range in self.ranges() {
+            match merged.last_mut() {
+                Some(last) if last.last.checked_add(1) == Some(range.first) => {
+                    last.last = range.last;
+                }
+                _ => merged.push(range),
+            }
+        }
+        self.ranges = merged
+            .iter()
+            .map(|range| (range.first, range.last))
+            .collect();
+    }
+
+    /// The merged ranges, in order.
+    fn ranges(&self) -> impl Iterator<Item = Range> + '_ {
+        self.ranges
+            .iter()
+            .map(|(&first, &last)| Range { first, last })
+    }
+
+    /// The merged range that could hold `value`: the last one starting at or
+    /// before it.
+    fn range_before(&self, value: u64) -> Option<Range> {
+        self.ranges
+            .range(..=value)
+            .next_back()
+            .map(|(&first, &last)| Range { first, last })
+    }
+
+    fn is_fresh(&self, value: u64) -> bool {
+        self.range_before(value)
+            .is_some_and(|range| range.check(value))
+    }
+
+    /// Counts the fresh values in a batch, walking the merged ranges alongside
+    /// the values when these are sorted, and searching per value otherwise.
+    fn count_fresh(&self, values: &[u64]) -> usize {
+        if !values.is_sorted() {
+            return values.iter().filter(|&&value| self.is_fresh(value)).count();
+        }
+
+        let mut ranges = self.ranges().peekable();
+        values
             .iter()
-            .copied()
-            .filter(|&value| {
-                // true if any range contains this value
-                self.ranges.iter().any(|range| range.check(value))
+            .filter(|&&value| {
+                // skip the ranges that end before this value (and so before all next ones)
+                while ranges.next_if(|range| range.last < value).is_some() {}
+                ranges.peek().is_some_and(|range| range.check(value))
             })
             .count()
     }
 
-    fn count_all_fresh_ingredients(&self) -> usize {
-        self.ranges.iter().map(|range| range.len()).sum::<u64>() as usize
+    /// The merged ranges sharing at least one id with `window`, in order.
+    fn ranges_within(&self, window: Range) -> impl Iterator<Item = Range> + '_ {
+        // the range holding `window.first` starts before it, so begin there
+        let start = self
+            .range_before(window.first)
+            .filter(|range| range.last >= window.first)
+            .map_or(window.first, |range| range.first);
+        self.ranges
+            .range(start..=window.last)
+            .map(|(&first, &last)| Range { first, last })
+    }
+
+    /// How many listed ingredients fall within `window`.
+    fn ingredients_within(&self, window: Range) -> usize {
+        self.ingredients.count_within(window)
+    }
+
+    /// The original ranges covering `value`, ordered by start.
+    fn covering(&self, value: u64) -> Vec<Source> {
+        self.sources.covering(value)
+    }
+
+    /// The spoiled stretches of `window`: the gaps between the merged ranges,
+    /// plus whatever lies before the first or after the last of them.
+    fn gaps(&self, window: Range) -> Vec<Range> {
+        let mut gaps = Vec::new();
+        // wide enough to step past `u64::MAX`
+        let mut cursor = u128::from(window.first);
+
+        for range in self.ranges_within(window) {
+            if u128::from(range.first) > cursor {
+                gaps.push(Range {
+                    first: cursor as u64,
+                    last: range.first - 1,
+                });
+            }
+            cursor = u128::from(range.last) + 1;
+        }
+        if cursor <= u128::from(window.last) {
+            gaps.push(Range {
+                first: cursor as u64,
+                last: window.last,
+            });
+        }
+        gaps
+    }
+
+    /// The widest gap within `window`, the first one on ties.
+    fn largest_gap(&self, window: Range) -> Option<Range> {
+        self.gaps(window)
+            .into_iter()
+            .rev()
+            .max_by_key(|gap| gap.len())
+    }
+
+    fn count_spoiled(&self, window: Range) -> u128 {
+        self.gaps(window).iter().map(|gap| gap.len()).sum()
+    }
+
+    /// The merged range closest to a spoiled `value` and how far away it is,
+    /// the lower one on ties.
+    fn nearest_fresh(&self, value: u64) -> Option<(Range, u64)> {
+        let below = self
+            .range_before(value)
+            .map(|range| (range, value - range.last));
+        let above = self
+            .ranges
+            .range((Bound::Excluded(value), Bound::Unbounded))
+            .next()
+            .map(|(&first, &last)| (Range { first, last }, first - value));
+
+        match (below, above) {
+            (Some(below), Some(above)) if above.1 < below.1 => Some(above),
+            (Some(below), _) => Some(below),
+            (None, above) => above,
+        }
+    }
+
+    /// The spoiled ingredients within `window`, with their nearest fresh
+    /// range and its distance.
+    fn spoiled_ingredients(&self, window: Range) -> Vec<(u64, Option<(Range, u64)>)> {
+        self.ingredients
+            .within(window)
+            .into_iter()
+            .filter(|&(value, _)| !self.is_fresh(value))
+            .flat_map(|(value, count)| {
+                std::iter::repeat_n((value, self.nearest_fresh(value)), count)
+            })
+            .collect()
+    }
+
+    fn count_fresh_ingredients(&self) -> usize {
+        self.fresh_ingredients
+    }
+
+    fn count_all_fresh_ingredients(&self) -> u128 {
+        self.fresh_ids
     }
 }
 
 fn main() -> io::Result<()> {
     let input = include_str!("input.data").trim();
-    let database = Database::from_str(input).unwrap();
+    let mut database = Database::from_str(input).unwrap();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    // `--merge-adjacent` also joins ranges that only touch, like `3-5` and `6-8`
+    if args.iter().any(|arg| arg == "--merge-adjacent") {
+        database.merge_adjacent();
+    }
+
+    // `--replay FILE` applies an edit log to the database before anything else,
+    // one `add FIRST-LAST`, `remove FIRST-LAST` or `ingredient ID` per line
+    if let Some(idx) = args.iter().position(|arg| arg == "--replay") {
+        let path = args.get(idx + 1).expect("Missing edit log path");
+        let log = fs::read_to_string(path)?;
+        for (idx, line) in log.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let edit = line.parse::<Edit>().map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{path}:{}: {err}", idx + 1),
+                )
+            })?;
+            database.apply(edit, idx + 1);
+            println!(
+                "{edit}: {} fresh ingredients, {} fresh ids",
+                database.count_fresh_ingredients(),
+                database.count_all_fresh_ingredients()
+            );
+        }
+    }
+
+    // `--check ID,ID,...` tells for each given id whether it is fresh
+    if let Some(idx) = args.iter().position(|arg| arg == "--check") {
+        let values = args
+            .get(idx + 1)
+            .expect("Missing ids to check")
+            .split(',')
+            .map(|value| value.trim().parse::<u64>().expect("Invalid id"))
+            .collect::<Vec<_>>();
+
+        for &value in &values {
+            let state = if database.is_fresh(value) {
+                "fresh"
+            } else {
+                "spoiled"
+            };
+            println!("{value}: {state}");
+        }
+        println!(
+            "{} of {} are fresh",
+            database.count_fresh(&values),
+            values.len()
+        );
+        return Ok(());
+    }
+
+    // `--covering ID,ID,...` lists the original ranges behind each given id
+    if let Some(idx) = args.iter().position(|arg| arg == "--covering") {
+        let values = args
+            .get(idx + 1)
+            .expect("Missing ids to look up")
+            .split(',')
+            .map(|value| value.trim().parse::<u64>().expect("Invalid id"));
+
+        for value in values {
+            let covering = database.covering(value);
+            println!("{value}: covered by {} range(s)", covering.len());
+            for source in covering {
+                println!("  {source}");
+            }
+        }
+        return Ok(());
+    }
+
+    // `--max-depth` finds an id covered by the most original ranges
+    if args.iter().any(|arg| arg == "--max-depth") {
+        match database.sources.max_depth() {
+            Some((value, depth)) => {
+                println!("{value} is covered by {depth} range(s), the most of any id");
+                for source in database.covering(value) {
+                    println!("  {source}");
+                }
+            }
+            None => println!("There are no ranges"),
+        }
+        return Ok(());
+    }
+
+    // `--gaps [FIRST-LAST]` reports the spoiled stretches within the window,
+    // by default the span of all the ranges
+    if let Some(idx) = args.iter().position(|arg| arg == "--gaps") {
+        let window = match args.get(idx + 1).filter(|arg| !arg.starts_with("--")) {
+            Some(window) => window.parse::<Range>().expect("Invalid window"),
+            None => match (
+                database.ranges.first_key_value(),
+                database.ranges.last_key_value(),
+            ) {
+                (Some((&first, _)), Some((_, &last))) => Range { first, last },
+                _ => {
+                    println!("There are no ranges");
+                    return Ok(());
+                }
+            },
+        };
+
+        let gaps = database.gaps(window);
+        println!("{} gap(s) within {window}:", gaps.len());
+        for gap in &gaps {
+            println!("  {gap} ({} ids)", gap.len());
+        }
+        if let Some(gap) = database.largest_gap(window) {
+            println!("Largest gap: {gap} ({} ids)", gap.len());
+        }
+        println!(
+            "Spoiled ids within {window}: {}",
+            database.count_spoiled(window)
+        );
+
+        let spoiled = database.spoiled_ingredients(window);
+        println!("{} spoiled ingredient(s) within {window}:", spoiled.len());
+        for (value, nearest) in spoiled {
+            match nearest {
+                Some((range, distance)) => {
+                    println!("  {value}: nearest fresh range {range}, {distance} away")
+                }
+                None => println!("  {value}: no fresh ranges"),
+            }
+        }
+        return Ok(());
+    }
+
     println!(
         "The answer for part one is {}",
         database.count_fresh_ingredients()
@@ -126,3 +452,107 @@ fn main() -> io::Result<()> {
     );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: u64 = u64::MAX;
+
+    fn database(ranges: &[(u64, u64)], ingredients: &[u64]) -> Database {
+        let sources = ranges
+            .iter()
+            .enumerate()
+            .map(|(idx, &(first, last))| Source {
+                range: Range { first, last },
+                origin: Origin::Input,
+                line: idx + 1,
+            })
+            .collect();
+        Database::new(sources, ingredients.to_vec())
+    }
+
+    fn whole() -> Range {
+        Range {
+            first: 0,
+            last: MAX,
+        }
+    }
+
+    #[test]
+    fn whole_domain_counts_every_id() {
+        let database = database(&[(0, MAX)], &[0, MAX]);
+        assert_eq!(database.count_all_fresh_ingredients(), u128::from(MAX) + 1);
+        assert_eq!(database.count_fresh_ingredients(), 2);
+        assert_eq!(database.gaps(whole()), vec![]);
+    }
+
+    #[test]
+    fn halves_cover_the_domain_without_merging() {
+        let database = database(&[(0, MAX / 2), (MAX / 2 + 1, MAX)], &[MAX / 2]);
+        assert_eq!(database.count_all_fresh_ingredients(), u128::from(MAX) + 1);
+        assert_eq!(database.count_fresh_ingredients(), 1);
+        assert_eq!(database.ranges().count(), 2);
+    }
+
+    #[test]
+    fn halves_merge_when_adjacent_ranges_are_merged() {
+        let mut database = database(&[(0, MAX / 2), (MAX / 2 + 1, MAX)], &[]);
+        database.merge_adjacent();
+        assert_eq!(database.ranges().collect::<Vec<_>>(), vec![whole()]);
+        assert_eq!(database.count_all_fresh_ingredients(), u128::from(MAX) + 1);
+    }
+
+    #[test]
+    fn single_ids_at_both_ends() {
+        let database = database(&[(0, 0), (MAX, MAX)], &[0, 1, MAX - 1, MAX]);
+        assert_eq!(database.count_all_fresh_ingredients(), 2);
+        assert_eq!(database.count_fresh_ingredients(), 2);
+        assert_eq!(database.count_spoiled(whole()), u128::from(MAX) - 1);
+    }
+
+    #[test]
+    fn nearest_fresh_range_at_both_ends() {
+        let database = database(&[(0, 0), (MAX, MAX)], &[]);
+        assert_eq!(
+            database.nearest_fresh(1),
+            Some((Range { first: 0, last: 0 }, 1))
+        );
+        assert_eq!(
+            database.nearest_fresh(MAX - 1),
+            Some((
+                Range {
+                    first: MAX,
+                    last: MAX
+                },
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn edits_up_to_the_end_of_the_domain() {
+        let mut database = database(&[(0, 9)], &[MAX]);
+        database.apply(
+            Edit::AddRange(Range {
+                first: 10,
+                last: MAX,
+            }),
+            1,
+        );
+        assert_eq!(database.count_all_fresh_ingredients(), u128::from(MAX) + 1);
+        assert_eq!(database.count_fresh_ingredients(), 1);
+
+        database.apply(
+            Edit::RemoveRange(Range {
+                first: 1,
+                last: MAX - 1,
+            }),
+            2,
+        );
+        assert_eq!(database.count_all_fresh_ingredients(), 2);
+        assert_eq!(database.count_fresh_ingredients(), 1);
+        assert_eq!(database.covering(MAX).len(), 1);
+        assert_eq!(database.covering(5), vec![]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::interval_tree::{Origin, Source};
+use super::{Database, Range};
+
+/// One change to the database, as written in an edit log:
+/// `add FIRST-LAST`, `remove FIRST-LAST` or `ingredient ID`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Edit {
+    AddRange(Range),
+    RemoveRange(Range),
+    AddIngredient(u64),
+}
+
+impl FromStr for Edit {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (command, argument) = input
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("Expected a command and an argument in: {input}"))?;
+        let range = || {
+            argument
+                .parse::<Range>()
+                .map_err(|err| format!("{err} in: {argument}"))
+        };
+
+        match command {
+            "add" => Ok(Edit::AddRange(range()?)),
+            "remove" => Ok(Edit::RemoveRange(range()?)),
+            "ingredient" => argument
+                .trim()
+                .parse::<u64>()
+                .map(Edit::AddIngredient)
+                .map_err(|_| format!("Invalid ingredient id: {argument}")),
+            _ => Err(format!("Unknown command: {command}")),
+        }
+    }
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::AddRange(range) => write!(f, "add {range}"),
+            Edit::RemoveRange(range) => write!(f, "remove {range}"),
+            Edit::AddIngredient(value) => write!(f, "ingredient {value}"),
+        }
+    }
+}
+
+impl Database {
+    /// Applies an edit read from line `line` of a log.
+    pub fn apply(&mut self, edit: Edit, line: usize) {
+        match edit {
+            Edit::AddRange(range) => self.add_range(Source {
+                range,
+                origin: Origin::Log,
+                line,
+            }),
+            Edit::RemoveRange(range) => self.remove_range(range),
+            Edit::AddIngredient(value) => self.add_ingredient(value),
+        }
+    }
+
+    /// Adds a fresh range, merging it with the ranges it overlaps (or touches).
+    /// Every merged range it swallows is removed once, and the ingredients in
+    /// each gap it fills are counted in logarithmic time, so the cost is
+    /// O((k + 1) log n) for k ranges touched.
+    pub fn add_range(&mut self, source: Source) {
+        let range = source.range;
+        for gap in self.gaps(range) {
+            self.fresh_ids += gap.len();
+            self.fresh_ingredients += self.ingredients_within(gap);
+        }
+
+        // when merging adjacent ranges, those ending right before or starting
+        // right after the new one are swallowed as well
+        let reach = if self.merge_adjacent {
+            Range {
+                first: range.first.saturating_sub(1),
+                last: range.last.saturating_add(1),
+            }
+        } else {
+            range
+        };
+
+        let mut merged = range;
+        for touched in self.ranges_within(reach).collect::<Vec<_>>() {
+            self.ranges.remove(&touched.first);
+            merged.first = merged.first.min(touched.first);
+            merged.last = merged.last.max(touched.last);
+        }
+        self.ranges.insert(merged.first, merged.last);
+        self.sources.insert(source);
+    }
+
+    /// Marks every id of `range` as spoiled, splitting the merged ranges (and
+    /// clipping the original ones) that stick out of it on either side.
+    pub fn remove_range(&mut self, range: Range) {
+        for touched in self.ranges_within(range).collect::<Vec<_>>() {
+            let cut = clip(touched, range);
+            self.fresh_ids -= cut.len();
+            self.fresh_ingredients -= self.ingredients_within(cut);
+
+            self.ranges.remove(&touched.first);
+            for piece in remainders(touched, cut) {
+                self.ranges.insert(piece.first, piece.last);
+            }
+        }
+
+        for source in self.sources.overlapping(range) {
+            self.sources.remove(&source);
+            for piece in remainders(source.range, clip(source.range, range)) {
+                self.sources.insert(Source {
+                    range: piece,
+                    ..source
+                });
+            }
+        }
+    }
+
+    pub fn add_ingredient(&mut self, value: u64) {
+        self.ingredients.insert(value);
+        if self.is_fresh(value) {
+            self.fresh_ingredients += 1;
+        }
+    }
+}
+
+/// The part of `range` inside `window`; the two must overlap.
+fn clip(range: Range, window: Range) -> Range {
+    Range {
+        first: range.first.max(window.first),
+        last: range.last.min(window.last),
+    }
+}
+
+/// What is left of `range` on either side once `cut` (a part of it) is gone.
+fn remainders(range: Range, cut: Range) -> impl Iterator<Item = Range> {
+    let before = (range.first < cut.first).then(|| Range {
+        first: range.first,
+        last: cut.first - 1,
+    });
+    let after = (cut.last < range.last).then(|| Range {
+        first: cut.last + 1,
+        last: range.last,
+    });
+    before.into_iter().chain(after)
+}
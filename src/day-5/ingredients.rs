@@ -0,0 +1,41 @@
+use super::Range;
+
+/// The listed ingredient ids as a sorted multiset, so counting the ids within
+/// a window takes two binary searches.
+#[derive(Debug)]
+pub struct Ingredients {
+    values: Vec<u64>,
+}
+
+impl Ingredients {
+    pub fn new() -> Self {
+        Ingredients { values: Vec::new() }
+    }
+
+    /// Lists `value` once more, shifting the larger ids up by one.
+    pub fn insert(&mut self, value: u64) {
+        let idx = self.values.partition_point(|&other| other <= value);
+        self.values.insert(idx, value);
+    }
+
+    /// The listed ids within `window`, as a sorted slice.
+    fn slice_within(&self, window: Range) -> &[u64] {
+        let start = self.values.partition_point(|&value| value < window.first);
+        let end = self.values.partition_point(|&value| value <= window.last);
+        &self.values[start..end]
+    }
+
+    /// How many listed ids fall within `window`.
+    pub fn count_within(&self, window: Range) -> usize {
+        self.slice_within(window).len()
+    }
+
+    /// The distinct ids within `window` with how many times each was listed,
+    /// in order.
+    pub fn within(&self, window: Range) -> Vec<(u64, usize)> {
+        self.slice_within(window)
+            .chunk_by(|a, b| a == b)
+            .map(|run| (run[0], run.len()))
+            .collect()
+    }
+}
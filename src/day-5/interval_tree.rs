@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::Range;
+
+/// Where a range was read from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    // the puzzle input
+    Input,
+    // an edit log replayed on top of it
+    Log,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Input => write!(f, "input"),
+            Origin::Log => write!(f, "log"),
+        }
+    }
+}
+
+/// A range as it was read, with the (1-based) line number in its origin.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Source {
+    pub range: Range,
+    pub origin: Origin,
+    pub line: usize,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} line {}: {}", self.origin, self.line, self.range)
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    source: Source,
+    priority: u64,
+    // largest `range.last` within the subtree rooted here
+    max_last: u64,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// Interval tree over the source ranges: a treap ordered by range start, where
+/// every node also knows the furthest end in its subtree, so stabbing queries
+/// can skip whole subtrees that end before the point.
+#[derive(Debug)]
+pub struct IntervalTree {
+    nodes: Vec<Node>,
+    // slots of removed nodes, reused by later insertions
+    free: Vec<usize>,
+    root: Option<usize>,
+    seed: u64,
+}
+
+impl IntervalTree {
+    pub fn new(sources: impl IntoIterator<Item = Source>) -> Self {
+        let mut tree = IntervalTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: None,
+            seed: 0x2545_f491_4f6c_dd1d,
+        };
+        for source in sources {
+            tree.insert(source);
+        }
+        tree
+    }
+
+    fn next_priority(&mut self) -> u64 {
+        // xorshift, the priorities only need to look random
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    fn update(&mut self, idx: usize) {
+        let node = &self.nodes[idx];
+        let max_last = [node.left, node.right]
+            .into_iter()
+            .flatten()
+            .map(|child| self.nodes[child].max_last)
+            .fold(node.source.range.last, u64::max);
+        self.nodes[idx].max_last = max_last;
+    }
+
+    fn rotate_right(&mut self, idx: usize) -> usize {
+        let left = self.nodes[idx].left.expect("rotation needs a left child");
+        self.nodes[idx].left = self.nodes[left].right;
+        self.nodes[left].right = Some(idx);
+        self.update(idx);
+        self.update(left);
+        left
+    }
+
+    fn rotate_left(&mut self, idx: usize) -> usize {
+        let right = self.nodes[idx].right.expect("rotation needs a right child");
+        self.nodes[idx].right = self.nodes[right].left;
+        self.nodes[right].left = Some(idx);
+        self.update(idx);
+        self.update(right);
+        right
+    }
+
+    pub fn insert(&mut self, source: Source) {
+        let node = Node {
+            source,
+            priority: self.next_priority(),
+            max_last: source.range.last,
+            left: None,
+            right: None,
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.root = Some(self.insert_at(self.root, idx));
+    }
+
+    fn insert_at(&mut self, root: Option<usize>, idx: usize) -> usize {
+        let Some(root) = root else {
+            return idx;
+        };
+
+        if self.nodes[idx].source < self.nodes[root].source {
+            let left = self.insert_at(self.nodes[root].left, idx);
+            self.nodes[root].left = Some(left);
+            self.update(root);
+            if self.nodes[left].priority > self.nodes[root].priority {
+                return self.rotate_right(root);
+            }
+        } else {
+            let right = self.insert_at(self.nodes[root].right, idx);
+            self.nodes[root].right = Some(right);
+            self.update(root);
+            if self.nodes[right].priority > self.nodes[root].priority {
+                return self.rotate_left(root);
+            }
+        }
+        root
+    }
+
+    /// Removes `source`, returning whether it was in the tree.
+    pub fn remove(&mut self, source: &Source) -> bool {
+        let (root, removed) = self.remove_at(self.root, source);
+        self.root = root;
+        removed
+    }
+
+    fn remove_at(&mut self, root: Option<usize>, source: &Source) -> (Option<usize>, bool) {
+        let Some(root) = root else {
+            return (None, false);
+        };
+
+        let removed = match source.cmp(&self.nodes[root].source) {
+            Ordering::Less => {
+                let (left, removed) = self.remove_at(self.nodes[root].left, source);
+                self.nodes[root].left = left;
+                removed
+            }
+            Ordering::Greater => {
+                let (right, removed) = self.remove_at(self.nodes[root].right, source);
+                self.nodes[root].right = right;
+                removed
+            }
+            Ordering::Equal => {
+                // rotate the node down until it has at most one child, then unlink it
+                let node = &self.nodes[root];
+                let rotated = match (node.left, node.right) {
+                    (None, child) | (child, None) => {
+                        self.free.push(root);
+                        return (child, true);
+                    }
+                    (Some(left), Some(right)) => {
+                        if self.nodes[left].priority > self.nodes[right].priority {
+                            self.rotate_right(root)
+                        } else {
+                            self.rotate_left(root)
+                        }
+                    }
+                };
+                return self.remove_at(Some(rotated), source);
+            }
+        };
+
+        self.update(root);
+        (Some(root), removed)
+    }
+
+    /// Sources covering `value`, ordered by range start.
+    pub fn covering(&self, value: u64) -> Vec<Source> {
+        self.overlapping(Range {
+            first: value,
+            last: value,
+        })
+    }
+
+    /// Sources sharing at least one id with `window`, ordered by range start.
+    pub fn overlapping(&self, window: Range) -> Vec<Source> {
+        let mut found = Vec::new();
+        self.collect_overlapping(self.root, window, &mut found);
+        found
+    }
+
+    fn collect_overlapping(&self, root: Option<usize>, window: Range, found: &mut Vec<Source>) {
+        let Some(root) = root else {
+            return;
+        };
+        let node = &self.nodes[root];
+        if node.max_last < window.first {
+            return; // everything below ends before the window
+        }
+
+        self.collect_overlapping(node.left, window, found);
+        if node.source.range.first <= window.last {
+            if node.source.range.last >= window.first {
+                found.push(node.source);
+            }
+            // only the right subtree can start after the window
+            self.collect_overlapping(node.right, window, found);
+        }
+    }
+
+    /// All sources, ordered by range start.
+    pub fn sources(&self) -> Vec<Source> {
+        let mut sources = Vec::with_capacity(self.nodes.len());
+        let mut stack = Vec::new();
+        let mut current = self.root;
+        while current.is_some() || !stack.is_empty() {
+            while let Some(idx) = current {
+                stack.push(idx);
+                current = self.nodes[idx].left;
+            }
+            let idx = stack.pop().expect("stack is not empty");
+            sources.push(self.nodes[idx].source);
+            current = self.nodes[idx].right;
+        }
+        sources
+    }
+
+    /// A point covered by the most sources and how many cover it.
+    pub fn max_depth(&self) -> Option<(u64, usize)> {
+        // +1 at every start, -1 just past every end; ends sort before starts at
+        // the same point so that touching ranges do not count as overlapping
+        let mut events = self
+            .sources()
+            .iter()
+            .flat_map(|source| {
+                let range = source.range;
+                [
+                    (u128::from(range.first), 1),
+                    (u128::from(range.last) + 1, -1),
+                ]
+            })
+            .collect::<Vec<(u128, i64)>>();
+        events.sort();
+
+        let mut depth = 0;
+        let mut best: Option<(u64, usize)> = None;
+        for (point, change) in events {
+            depth += change;
+            if change > 0 && best.is_none_or(|(_, best)| depth as usize > best) {
+                best = Some((point as u64, depth as usize));
+            }
+        }
+        best
+    }
+}
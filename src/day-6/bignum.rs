@@ -0,0 +1,240 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::{Number, Operation};
+
+// powers wider than this are refused rather than computed: multiplying and
+// printing are both quadratic, and this size still finishes promptly
+const MAX_BITS: u64 = 1 << 16;
+
+/// Arbitrary size unsigned integer, as base 2^32 limbs with the least
+/// significant first and no trailing zero limbs (so zero has none).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn normalized(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn bits(&self) -> u64 {
+        self.limbs.last().map_or(0, |&top| {
+            (self.limbs.len() as u64 - 1) * 32 + u64::from(32 - top.leading_zeros())
+        })
+    }
+
+    fn bit(&self, idx: u64) -> bool {
+        self.limbs
+            .get((idx / 32) as usize)
+            .is_some_and(|limb| limb >> (idx % 32) & 1 == 1)
+    }
+
+    fn add(&self, other: &BigUint) -> BigUint {
+        let mut limbs = Vec::with_capacity(self.limbs.len().max(other.limbs.len()) + 1);
+        let mut carry = 0u64;
+        for idx in 0..self.limbs.len().max(other.limbs.len()) {
+            let sum = u64::from(self.limbs.get(idx).copied().unwrap_or(0))
+                + u64::from(other.limbs.get(idx).copied().unwrap_or(0))
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint::normalized(limbs)
+    }
+
+    fn checked_sub(&self, other: &BigUint) -> Option<BigUint> {
+        if *self < *other {
+            return None;
+        }
+
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (idx, &limb) in self.limbs.iter().enumerate() {
+            let mut difference =
+                i64::from(limb) - i64::from(other.limbs.get(idx).copied().unwrap_or(0)) - borrow;
+            borrow = i64::from(difference < 0);
+            if difference < 0 {
+                difference += 1 << 32;
+            }
+            limbs.push(difference as u32);
+        }
+        Some(BigUint::normalized(limbs))
+    }
+
+    fn mul(&self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &left) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &right) in other.limbs.iter().enumerate() {
+                let product = u64::from(left) * u64::from(right) + u64::from(limbs[i + j]) + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint::normalized(limbs)
+    }
+
+    /// Quotient and remainder by a single limb.
+    fn div_small(&self, divisor: u32) -> (BigUint, u32) {
+        let mut limbs = vec![0u32; self.limbs.len()];
+        let mut remainder = 0u64;
+        for (idx, &limb) in self.limbs.iter().enumerate().rev() {
+            let current = remainder << 32 | u64::from(limb);
+            limbs[idx] = (current / u64::from(divisor)) as u32;
+            remainder = current % u64::from(divisor);
+        }
+        (BigUint::normalized(limbs), remainder as u32)
+    }
+
+    /// Quotient and remainder, by shifting in one bit at a time; the divisor
+    /// must not be zero.
+    fn div_rem(&self, divisor: &BigUint) -> (BigUint, BigUint) {
+        if let [small] = divisor.limbs[..] {
+            let (quotient, remainder) = self.div_small(small);
+            return (quotient, BigUint::from(u64::from(remainder)));
+        }
+
+        let mut quotient = vec![0u32; self.limbs.len()];
+        let mut remainder = BigUint::from(0);
+        for idx in (0..self.bits()).rev() {
+            remainder = remainder.add(&remainder);
+            if self.bit(idx) {
+                remainder = remainder.add(&BigUint::from(1));
+            }
+            if let Some(rest) = remainder.checked_sub(divisor) {
+                remainder = rest;
+                quotient[(idx / 32) as usize] |= 1 << (idx % 32);
+            }
+        }
+        (BigUint::normalized(quotient), remainder)
+    }
+
+    fn checked_pow(&self, exponent: &BigUint) -> Option<BigUint> {
+        if self.bits() <= 1 || exponent.is_zero() {
+            // 0 and 1 stay themselves, anything else to the 0th is 1
+            return Some(if exponent.is_zero() {
+                BigUint::from(1)
+            } else {
+                self.clone()
+            });
+        }
+        if exponent.bits() > 32 || self.bits() * exponent.low_u64() > MAX_BITS {
+            return None;
+        }
+
+        let mut result = BigUint::from(1);
+        for idx in (0..exponent.bits()).rev() {
+            result = result.mul(&result);
+            if exponent.bit(idx) {
+                result = result.mul(self);
+            }
+        }
+        Some(result)
+    }
+
+    /// The value itself, for numbers known to fit in a u64.
+    fn low_u64(&self) -> u64 {
+        self.limbs
+            .iter()
+            .rev()
+            .fold(0, |value, &limb| value << 32 | u64::from(limb))
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        BigUint::normalized(vec![value as u32, (value >> 32) as u32])
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // peel off nine decimal digits at a time
+        let mut chunks = Vec::new();
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, chunk) = rest.div_small(1_000_000_000);
+            chunks.push(chunk);
+            rest = quotient;
+        }
+
+        let mut chunks = chunks.iter().rev();
+        if let Some(top) = chunks.next() {
+            write!(f, "{top}")?;
+        }
+        chunks.try_for_each(|chunk| write!(f, "{chunk:09}"))
+    }
+}
+
+impl Number for BigUint {
+    fn from_u64(value: u64) -> Self {
+        BigUint::from(value)
+    }
+
+    fn apply(operation: Operation, left: &Self, right: &Self) -> Result<Self, String> {
+        let result = match operation {
+            Operation::Add => Some(left.add(right)),
+            Operation::Multiply => Some(left.mul(right)),
+            Operation::Subtract => {
+                return left.checked_sub(right).ok_or_else(|| {
+                    format!("{left} {} {right} is out of range", operation.symbol())
+                });
+            }
+            Operation::Divide | Operation::Modulo if right.is_zero() => {
+                return Err(format!("{left} {} 0 divides by zero", operation.symbol()));
+            }
+            Operation::Divide => Some(left.div_rem(right).0),
+            Operation::Modulo => Some(left.div_rem(right).1),
+            Operation::Power => left.checked_pow(right),
+            Operation::Min => Some(left.min(right).clone()),
+            Operation::Max => Some(left.max(right).clone()),
+            Operation::Concatenate => {
+                let digits = right.to_string().len() as u64;
+                BigUint::from(10)
+                    .checked_pow(&BigUint::from(digits))
+                    .map(|shift| left.mul(&shift).add(right))
+            }
+        };
+        // only the powers can be refused here, for being wider than `MAX_BITS`
+        result.ok_or_else(|| {
+            format!(
+                "{left} {} {right} is too large to compute, over {MAX_BITS} bits",
+                operation.symbol()
+            )
+        })
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self.add(other))
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Layout, Problem};
+
+/// Renders the problems as a worksheet in `layout`, which the matching parser
+/// reads back into the same problems.
+pub fn format_worksheet(problems: &[Problem], layout: Layout) -> Result<String, String> {
+    let lines = match layout {
+        Layout::Rows => format_rows(problems)?,
+        Layout::Columns => format_columns(problems)?,
+    };
+    Ok(lines
+        .iter()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// One problem per column, its numbers stacked one per row and its operator
+/// on the last line; the rows are read by position, so every problem needs
+/// the same count of numbers.
+fn format_rows(problems: &[Problem]) -> Result<Vec<String>, String> {
+    let height = problems.first().map_or(0, |problem| problem.numbers.len());
+    if let Some(idx) = problems
+        .iter()
+        .position(|problem| problem.numbers.len() != height)
+    {
+        return Err(format!(
+            "Problem {} has {} numbers, the row layout needs {height} for every problem",
+            idx + 1,
+            problems[idx].numbers.len()
+        ));
+    }
+
+    let widths = problems
+        .iter()
+        .map(|problem| {
+            problem
+                .numbers
+                .iter()
+                .map(|number| number.to_string().len())
+                .max()
+                .unwrap_or(1)
+        })
+        .collect::<Vec<_>>();
+
+    let mut lines = (0..height)
+        .map(|row| {
+            problems
+                .iter()
+                .zip(&widths)
+                .map(|(problem, width)| format!("{:<width$}", problem.numbers[row]))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    lines.push(
+        problems
+            .iter()
+            .zip(&widths)
+            .map(|(problem, width)| format!("{:<width$}", problem.operation.symbol()))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    Ok(lines)
+}
+
+/// The part two layout: every number is a column of digits read top to
+/// bottom, a problem is a block of such columns with its operator under the
+/// first one, and blocks are separated by a blank column.
+fn format_columns(problems: &[Problem]) -> Result<Vec<String>, String> {
+    if let Some(idx) = problems
+        .iter()
+        .position(|problem| problem.numbers.is_empty())
+    {
+        return Err(format!(
+            "Problem {} has no numbers to write as columns",
+            idx + 1
+        ));
+    }
+
+    let digits = problems
+        .iter()
+        .map(|problem| {
+            problem
+                .numbers
+                .iter()
+                .map(|number| number.to_string().chars().collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let height = digits.iter().flatten().map(Vec::len).max().unwrap_or(0);
+
+    // digits hang from the top row, so no row starts with a blank
+    let mut lines = (0..height)
+        .map(|row| {
+            digits
+                .iter()
+                .map(|block| {
+                    block
+                        .iter()
+                        .map(|column| column.get(row).copied().unwrap_or(' '))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    lines.push(
+        problems
+            .iter()
+            .map(|problem| {
+                format!(
+                    "{:<width$}",
+                    problem.operation.symbol(),
+                    width = problem.numbers.len()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    Ok(lines)
+}
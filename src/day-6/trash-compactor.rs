@@ -1,39 +1,196 @@
+use std::env;
+use std::fmt;
 use std::io;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+mod bignum;
+mod format;
+
+use bignum::BigUint;
+use format::format_worksheet;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 enum Operation {
     Add,
     Multiply,
+    Subtract,
+    Divide,
+    Modulo,
+    Power,
+    Min,
+    Max,
+    // joins the digits: 12 | 34 = 1234
+    Concatenate,
+}
+
+/// How a problem's numbers are grouped when folded with its operation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Associativity {
+    // any grouping gives the same result
+    Both,
+    // ((a op b) op c)
+    Left,
+    // (a op (b op c))
+    Right,
 }
 
 impl Operation {
-    fn apply(&self, left: u64, right: u64) -> u64 {
+    const ALL: [Operation; 9] = [
+        Operation::Add,
+        Operation::Multiply,
+        Operation::Subtract,
+        Operation::Divide,
+        Operation::Modulo,
+        Operation::Power,
+        Operation::Min,
+        Operation::Max,
+        Operation::Concatenate,
+    ];
+
+    fn symbol(&self) -> char {
+        match self {
+            Operation::Add => '+',
+            Operation::Multiply => '*',
+            Operation::Subtract => '-',
+            Operation::Divide => '/',
+            Operation::Modulo => '%',
+            Operation::Power => '^',
+            Operation::Min => '<',
+            Operation::Max => '>',
+            Operation::Concatenate => '|',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Result<Self, String> {
+        Operation::ALL
+            .into_iter()
+            .find(|operation| operation.symbol() == symbol)
+            .ok_or_else(|| format!("Invalid operator: {symbol}"))
+    }
+
+    /// The value folding starts from, so that a problem without numbers has
+    /// a result too; the operations without one start from their first
+    /// operand instead.
+    fn identity(&self) -> Option<u64> {
         match self {
-            Operation::Add => left + right,
-            Operation::Multiply => left * right,
+            Operation::Add | Operation::Max => Some(0),
+            Operation::Multiply => Some(1),
+            Operation::Min => Some(u64::MAX),
+            // 0 | x = x (but x | 0 = x0)
+            Operation::Concatenate => Some(0),
+            Operation::Subtract | Operation::Divide | Operation::Modulo | Operation::Power => None,
         }
     }
+
+    fn associativity(&self) -> Associativity {
+        match self {
+            Operation::Add | Operation::Multiply | Operation::Min | Operation::Max => {
+                Associativity::Both
+            }
+            // zeros break it: (1 | 0) | 2 = 102 but 1 | (0 | 2) = 12
+            Operation::Concatenate => Associativity::Left,
+            Operation::Subtract | Operation::Divide | Operation::Modulo => Associativity::Left,
+            Operation::Power => Associativity::Right,
+        }
+    }
+
+    fn apply(&self, left: u64, right: u64) -> Result<u64, String> {
+        let result = match self {
+            Operation::Add => left.checked_add(right),
+            Operation::Multiply => left.checked_mul(right),
+            Operation::Subtract => left.checked_sub(right),
+            Operation::Divide | Operation::Modulo if right == 0 => {
+                return Err(format!("{left} {} 0 divides by zero", self.symbol()));
+            }
+            Operation::Divide => Some(left / right),
+            Operation::Modulo => Some(left % right),
+            Operation::Power => u32::try_from(right)
+                .ok()
+                .and_then(|exponent| left.checked_pow(exponent)),
+            Operation::Min => Some(left.min(right)),
+            Operation::Max => Some(left.max(right)),
+            Operation::Concatenate => {
+                let digits = right.checked_ilog10().unwrap_or(0) + 1;
+                10u64
+                    .checked_pow(digits)
+                    .and_then(|shift| left.checked_mul(shift))
+                    .and_then(|shifted| shifted.checked_add(right))
+            }
+        };
+        result.ok_or_else(|| format!("{left} {} {right} is out of range", self.symbol()))
+    }
+}
+
+/// Integer type a worksheet can be computed in.
+trait Number: Clone + fmt::Display {
+    fn from_u64(value: u64) -> Self;
+    fn apply(operation: Operation, left: &Self, right: &Self) -> Result<Self, String>;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+}
+
+impl Number for u64 {
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+
+    fn apply(operation: Operation, left: &Self, right: &Self) -> Result<Self, String> {
+        operation.apply(*left, *right)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct Problem {
     numbers: Vec<u64>,
     operation: Operation,
 }
 
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = format!(" {} ", self.operation.symbol());
+        let numbers = self
+            .numbers
+            .iter()
+            .map(|number| number.to_string())
+            .collect::<Vec<_>>();
+        // a lone number would otherwise hide which operation it stands for
+        if numbers.len() < 2 {
+            write!(f, "{} ", self.operation.symbol())?;
+        }
+        write!(f, "{}", numbers.join(&separator))
+    }
+}
+
 impl Problem {
-    fn solve(&self) -> u64 {
-        self.numbers.iter().copied().fold(
-            match self.operation {
-                Operation::Add => 0,
-                Operation::Multiply => 1,
-            },
-            |accumulator, value| self.operation.apply(accumulator, value),
-        )
+    fn solve<N: Number>(&self) -> Result<N, String> {
+        let operation = self.operation;
+        let mut numbers = self.numbers.iter().map(|&number| N::from_u64(number));
+
+        // right-associative operations fold from the last number backwards
+        let right = operation.associativity() == Associativity::Right;
+        let seed = match operation.identity() {
+            Some(identity) => Some(N::from_u64(identity)),
+            None if right => numbers.next_back(),
+            None => numbers.next(),
+        }
+        .ok_or_else(|| format!("Nothing to apply {} to", operation.symbol()))?;
+
+        if right {
+            numbers.try_rfold(seed, |accumulator, value| {
+                N::apply(operation, &value, &accumulator)
+            })
+        } else {
+            numbers.try_fold(seed, |accumulator, value| {
+                N::apply(operation, &accumulator, &value)
+            })
+        }
     }
 }
 
-fn parse_problems_for_part_one(input: &str) -> Vec<Problem> {
+fn parse_problems_for_part_one(input: &str) -> Result<Vec<Problem>, String> {
     let mut lines = input
         .lines()
         .map(str::trim)
@@ -43,14 +200,16 @@ fn parse_problems_for_part_one(input: &str) -> Vec<Problem> {
     // parse operations
     let operations = lines
         .pop()
-        .unwrap()
+        .ok_or("Input is empty")?
         .split_whitespace()
-        .map(|token| match token {
-            "+" => Operation::Add,
-            "*" => Operation::Multiply,
-            other => panic!("Invalid operator: {}", other),
+        .map(|token| {
+            let mut symbols = token.chars();
+            match (symbols.next(), symbols.next()) {
+                (Some(symbol), None) => Operation::from_symbol(symbol),
+                _ => Err(format!("Invalid operator: {token}")),
+            }
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
     // prepare problems array
     let mut problems = vec![
@@ -62,11 +221,19 @@ fn parse_problems_for_part_one(input: &str) -> Vec<Problem> {
     ];
 
     // fill numbers column-wise
-    for line in lines {
+    for (row, line) in lines.into_iter().enumerate() {
         let numbers = line
             .split_whitespace()
-            .map(|n| n.parse::<u64>().unwrap())
-            .collect::<Vec<_>>();
+            .map(|n| n.parse::<u64>().map_err(|_| format!("Invalid number: {n}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        if numbers.len() > problems.len() {
+            return Err(format!(
+                "Row {} has {} numbers for {} operators",
+                row + 1,
+                numbers.len(),
+                problems.len()
+            ));
+        }
 
         for (idx, number) in numbers.into_iter().enumerate() {
             problems[idx].numbers.push(number);
@@ -78,90 +245,387 @@ fn parse_problems_for_part_one(input: &str) -> Vec<Problem> {
         problem.operation = operation;
     }
 
-    problems
+    Ok(problems)
 }
 
-fn parse_problems_for_part_two(input: &str) -> Vec<Problem> {
-    // keep lines as-is (no trim per line) to preserve horizontal spacing.
-    let lines = input.lines().collect::<Vec<_>>();
-    let grid_height = lines.len() - 1;
-    let grid_width = lines
-        .iter()
-        .map(|line| line.chars().count())
-        .max()
-        .unwrap_or(0);
+// tabs advance to the next multiple of this many columns
+const TAB_WIDTH: usize = 8;
 
-    // build rectangular grid [row][col] of chars, padding right with spaces.
-    let mut grid = Vec::with_capacity(grid_height);
-    for line in &lines[..grid_height] {
-        let mut row = line.chars().collect::<Vec<_>>();
-        if row.len() < grid_width {
-            row.resize(grid_width, ' ');
+/// Line as a row of cells, with tabs expanded and trailing whitespace dropped.
+fn expand_tabs(line: &str) -> Vec<char> {
+    let mut row = Vec::new();
+    for symbol in line.trim_end().chars() {
+        if symbol == '\t' {
+            let stop = (row.len() / TAB_WIDTH + 1) * TAB_WIDTH;
+            row.resize(stop, ' ');
+        } else {
+            row.push(symbol);
         }
-        grid.push(row);
     }
+    row
+}
 
-    // get segments info
-    let operations_line = lines.last().expect("Input is empty");
-    let mut segments_positions = operations_line
-        .chars()
-        .enumerate()
-        .filter_map(|(idx, token)| match token {
-            '+' | '*' => Some(idx),
-            _ => None,
-        })
+fn parse_problems_for_part_two(input: &str) -> Result<Vec<Problem>, String> {
+    // keep lines as-is (no trim at the start) to preserve horizontal spacing.
+    let mut grid = input
+        .lines()
+        .map(expand_tabs)
+        .filter(|row| !row.is_empty())
         .collect::<Vec<_>>();
-    segments_positions.push(grid_width + 1); // sentinel at the end to make windows cover [start, end)
+    let operations = grid.pop().ok_or("Input is empty")?;
+    let grid_width = grid
+        .iter()
+        .chain([&operations])
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0);
 
-    // parse numbers
-    let mut problems = Vec::new();
+    let cell = |row: &Vec<char>, col: usize| row.get(col).copied().unwrap_or(' ');
+    let is_separator = |col: usize| {
+        grid.iter()
+            .chain([&operations])
+            .all(|row| cell(row, col) == ' ')
+    };
 
-    let height = grid.len();
-    let operations_line = operations_line.chars().collect::<Vec<_>>();
-    for window in segments_positions.windows(2) {
-        let start = window[0];
-        let end = window[1] - 1;
+    // problems are the runs of columns between fully blank ones
+    let mut blocks = Vec::new();
+    let mut start = None;
+    for col in 0..=grid_width {
+        match (start, col == grid_width || is_separator(col)) {
+            (None, false) => start = Some(col),
+            (Some(first), true) => {
+                blocks.push(first..col);
+                start = None;
+            }
+            _ => {}
+        }
+    }
 
-        let operation = match operations_line[start] {
-            '+' => Operation::Add,
-            '*' => Operation::Multiply,
-            _ => unreachable!("segment start must be an operator"),
-        };
+    let mut problems = Vec::new();
+    for block in blocks {
+        // the operator may sit under any column of its block
+        let mut symbols = block.clone().filter(|&col| cell(&operations, col) != ' ');
+        let col = symbols
+            .next()
+            .ok_or_else(|| format!("Column {}: no operator under this problem", block.start + 1))?;
+        if let Some(extra) = symbols.next() {
+            return Err(format!(
+                "Column {}: second operator under the problem starting at column {}",
+                extra + 1,
+                block.start + 1
+            ));
+        }
+        let operation = Operation::from_symbol(cell(&operations, col))
+            .map_err(|err| format!("Column {}: {err}", col + 1))?;
 
         let mut numbers = Vec::new();
-        for x in start..end {
+        for col in block {
             let mut digits = String::new();
-            for y in 0..height {
-                let ch = grid[y][x];
-                if ch.is_ascii_digit() {
-                    digits.push(ch);
+            for (row, line) in grid.iter().enumerate() {
+                match cell(line, col) {
+                    ' ' => {}
+                    digit if digit.is_ascii_digit() => digits.push(digit),
+                    other => {
+                        return Err(format!(
+                            "Column {}, row {}: unexpected '{other}'",
+                            col + 1,
+                            row + 1
+                        ));
+                    }
                 }
             }
 
-            numbers.push(digits.parse::<u64>().unwrap());
+            // a column holding only the operator is not a number
+            if !digits.is_empty() {
+                let number = digits
+                    .parse::<u64>()
+                    .map_err(|_| format!("Column {}: {digits} is too large", col + 1))?;
+                numbers.push(number);
+            }
         }
 
         problems.push(Problem { numbers, operation });
     }
 
+    Ok(problems)
+}
+
+/// The two ways a worksheet is written down.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Layout {
+    // part one: the numbers of a problem are stacked in rows
+    Rows,
+    // part two: every number is a vertical column of digits
+    Columns,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "rows" => Ok(Layout::Rows),
+            "columns" => Ok(Layout::Columns),
+            other => Err(format!(
+                "Unknown layout: {other} (expected rows or columns)"
+            )),
+        }
+    }
+}
+
+impl Layout {
+    fn parse(&self, input: &str) -> Result<Vec<Problem>, String> {
+        match self {
+            Layout::Rows => parse_problems_for_part_one(input),
+            Layout::Columns => parse_problems_for_part_two(input),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Rows => write!(f, "rows"),
+            Layout::Columns => write!(f, "columns"),
+        }
+    }
+}
+
+/// One of the worksheets stacked in the input; a `[rows]` or `[columns]`
+/// first line pins the layout it is read in.
+#[derive(Debug)]
+struct Worksheet {
+    text: String,
+    layout: Option<Layout>,
+}
+
+impl Worksheet {
+    /// The problems, read in the pinned layout or else in `layout`.
+    fn problems(&self, layout: Layout) -> Result<Vec<Problem>, String> {
+        self.layout.unwrap_or(layout).parse(&self.text)
+    }
+}
+
+/// Splits the input into worksheets at blank lines.
+fn split_worksheets(input: &str) -> Result<Vec<Worksheet>, String> {
+    let mut blocks = vec![Vec::new()];
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            blocks.push(Vec::new());
+        } else if let Some(block) = blocks.last_mut() {
+            block.push(line);
+        }
+    }
+
+    blocks
+        .into_iter()
+        .filter(|block| !block.is_empty())
+        .map(|mut block| {
+            let header = block[0].trim();
+            let layout = match header.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+                Some(layout) => {
+                    block.remove(0);
+                    Some(layout.parse::<Layout>()?)
+                }
+                None => None,
+            };
+            Ok(Worksheet {
+                text: block.join("\n"),
+                layout,
+            })
+        })
+        .collect()
+}
+
+/// Prefixes an error with the worksheet it came from, when there are several.
+fn in_worksheet(worksheets: &[Worksheet], idx: usize) -> impl Fn(String) -> String {
+    let several = worksheets.len() > 1;
+    move |err| {
+        if several {
+            format!("Worksheet {}: {err}", idx + 1)
+        } else {
+            err
+        }
+    }
+}
+
+/// Writes every worksheet in `target`, keeping the pins (now to `target`).
+fn render_worksheets(
+    worksheets: &[Worksheet],
+    from: Layout,
+    target: Layout,
+) -> Result<String, String> {
+    worksheets
+        .iter()
+        .enumerate()
+        .map(|(idx, worksheet)| {
+            let text = worksheet
+                .problems(from)
+                .and_then(|problems| format_worksheet(&problems, target))
+                .map_err(in_worksheet(worksheets, idx))?;
+            Ok(match worksheet.layout {
+                Some(_) => format!("[{target}]\n{text}"),
+                None => text,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(|texts| texts.join("\n\n"))
+}
+
+/// Whether reading every worksheet, writing it back in its own layout and
+/// reading that again gives the same problems.
+fn round_trips(worksheets: &[Worksheet], from: Layout) -> Result<bool, String> {
+    for (idx, worksheet) in worksheets.iter().enumerate() {
+        let layout = worksheet.layout.unwrap_or(from);
+        let problems = layout
+            .parse(&worksheet.text)
+            .map_err(in_worksheet(worksheets, idx))?;
+        let text = format_worksheet(&problems, layout).map_err(in_worksheet(worksheets, idx))?;
+        if layout.parse(&text).map_err(in_worksheet(worksheets, idx))? != problems {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Prints every problem with its result, or why it has none.
+fn print_breakdown<N: Number>(worksheets: &[Worksheet], from: Layout) -> Result<(), String> {
+    for (idx, worksheet) in worksheets.iter().enumerate() {
+        let problems = worksheet
+            .problems(from)
+            .map_err(in_worksheet(worksheets, idx))?;
+        if worksheets.len() > 1 {
+            println!("Worksheet {}:", idx + 1);
+        }
+        for (idx, problem) in problems.iter().enumerate() {
+            match problem.solve::<N>() {
+                Ok(result) => println!("Problem {}: {problem} = {result}", idx + 1),
+                Err(err) => println!("Problem {}: {problem} fails: {err}", idx + 1),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_answer<N: Number>(problems: Vec<Problem>) -> Result<N, String> {
     problems
+        .iter()
+        .enumerate()
+        .try_fold(N::from_u64(0), |total, (idx, problem)| {
+            let result = problem
+                .solve::<N>()
+                .map_err(|err| format!("Problem {}: {err}", idx + 1))?;
+            total
+                .checked_add(&result)
+                .ok_or_else(|| format!("The total is out of range at problem {}", idx + 1))
+        })
 }
 
-fn get_answer(problems: Vec<Problem>) -> u64 {
-    problems.iter().map(Problem::solve).sum()
+/// Total of every worksheet and the grand total over all of them.
+fn get_totals<N: Number>(worksheets: &[Worksheet], layout: Layout) -> Result<(Vec<N>, N), String> {
+    let totals = worksheets
+        .iter()
+        .enumerate()
+        .map(|(idx, worksheet)| {
+            worksheet
+                .problems(layout)
+                .and_then(get_answer::<N>)
+                .map_err(in_worksheet(worksheets, idx))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let total = totals
+        .iter()
+        .try_fold(N::from_u64(0), |total, value| total.checked_add(value))
+        .ok_or("The grand total is out of range")?;
+    Ok((totals, total))
+}
+
+/// Totals of the worksheets read (unless pinned) in `layout` and their grand
+/// total, in big integers when `big`.
+fn get_answer_for(
+    worksheets: &[Worksheet],
+    layout: Layout,
+    big: bool,
+) -> Result<(Vec<String>, String), String> {
+    fn to_strings<N: Number>((totals, total): (Vec<N>, N)) -> (Vec<String>, String) {
+        (
+            totals.iter().map(ToString::to_string).collect(),
+            total.to_string(),
+        )
+    }
+
+    if big {
+        get_totals::<BigUint>(worksheets, layout).map(to_strings)
+    } else {
+        get_totals::<u64>(worksheets, layout).map(to_strings)
+    }
 }
 
 fn main() -> io::Result<()> {
-    let input = include_str!("input.data").trim();
-
-    println!(
-        "The answer for part one is {}",
-        get_answer(parse_problems_for_part_one(input))
-    );
-    println!(
-        "The answer for part two is {}",
-        get_answer(parse_problems_for_part_two(input))
-    );
+    // only the end is trimmed: leading spaces on the first row are part of
+    // the column layout
+    let input = include_str!("input.data").trim_end();
+
+    let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+    let worksheets = split_worksheets(input).map_err(invalid)?;
+
+    // `--render rows|columns [--from rows|columns]` reads the worksheets (by
+    // default as rows) and writes them back in the given layout
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let layout_after = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|idx| {
+                args.get(idx + 1)
+                    .ok_or_else(|| format!("Missing layout after {flag}"))?
+                    .parse::<Layout>()
+            })
+            .transpose()
+    };
+    let from = layout_after("--from")
+        .map_err(invalid)?
+        .unwrap_or(Layout::Rows);
+
+    if let Some(target) = layout_after("--render").map_err(invalid)? {
+        println!(
+            "{}",
+            render_worksheets(&worksheets, from, target).map_err(invalid)?
+        );
+        return Ok(());
+    }
+
+    // `--round-trip [--from rows|columns]` checks that every worksheet written
+    // back in its own layout reads to the same problems
+    if args.iter().any(|arg| arg == "--round-trip") {
+        let verified = round_trips(&worksheets, from).map_err(invalid)?;
+        println!("Worksheets round-trip: {verified}");
+        return Ok(());
+    }
+
+    // `--big` computes in big integers, which do not overflow; only `^` results
+    // wider than 65,536 bits are refused, as too large to compute
+    let big = args.iter().any(|arg| arg == "--big");
+
+    // `--breakdown [--from rows|columns]` lists every problem with its result
+    if args.iter().any(|arg| arg == "--breakdown") {
+        if big {
+            print_breakdown::<BigUint>(&worksheets, from).map_err(invalid)?;
+        } else {
+            print_breakdown::<u64>(&worksheets, from).map_err(invalid)?;
+        }
+        return Ok(());
+    }
+
+    for (part, layout) in [("one", Layout::Rows), ("two", Layout::Columns)] {
+        let (totals, total) = get_answer_for(&worksheets, layout, big).map_err(invalid)?;
+        // the per worksheet totals only say something when there are several
+        if totals.len() > 1 {
+            for (idx, worksheet_total) in totals.iter().enumerate() {
+                println!("Part {part}, worksheet {}: {worksheet_total}", idx + 1);
+            }
+        }
+        println!("The answer for part {part} is {total}");
+    }
 
     Ok(())
 }
@@ -24,9 +24,12 @@ fn load_points(input: &str) -> Vec<Point> {
             let (x, rest) = line.split_once(",").unwrap();
             let (y, z) = rest.split_once(",").unwrap();
             Point {
-                x: x.parse().expect(&format!("Cannot parse {} to i64", x)),
-                y: y.parse().expect(&format!("Cannot parse {} to i64", y)),
-                z: z.parse().expect(&format!("Cannot parse {} to i64", z)),
+                x: x.parse()
+                    .unwrap_or_else(|_| panic!("Cannot parse {} to i64", x)),
+                y: y.parse()
+                    .unwrap_or_else(|_| panic!("Cannot parse {} to i64", y)),
+                z: z.parse()
+                    .unwrap_or_else(|_| panic!("Cannot parse {} to i64", z)),
             }
         })
         .collect::<Vec<_>>()
@@ -94,7 +97,7 @@ impl DisjointSet {
     }
 }
 
-fn solution_for_part_one(points: &Vec<Point>, edges: &Vec<Edge>, connection_count: usize) -> usize {
+fn solution_for_part_one(points: &[Point], edges: &[Edge], connection_count: usize) -> usize {
     let mut dsu = DisjointSet::new(points.len());
 
     for edge in edges.iter().take(connection_count) {
@@ -118,7 +121,7 @@ fn solution_for_part_one(points: &Vec<Point>, edges: &Vec<Edge>, connection_coun
     circuits_sizes[0] * circuits_sizes[1] * circuits_sizes[2]
 }
 
-fn solution_for_part_two(points: &Vec<Point>, edges: &Vec<Edge>) -> usize {
+fn solution_for_part_two(points: &[Point], edges: &[Edge]) -> usize {
     let mut components = points.len();
     let mut dsu = DisjointSet::new(components);
 
@@ -12,8 +12,10 @@ fn load_points(input: &str) -> Vec<Point> {
         .map(|line| {
             let (x, y) = line.split_once(",").unwrap();
             Point {
-                x: x.parse().expect(&format!("Cannot parse {} to i64", x)),
-                y: y.parse().expect(&format!("Cannot parse {} to i64", y)),
+                x: x.parse()
+                    .unwrap_or_else(|_| panic!("Cannot parse {} to i64", x)),
+                y: y.parse()
+                    .unwrap_or_else(|_| panic!("Cannot parse {} to i64", y)),
             }
         })
         .collect::<Vec<_>>()